@@ -0,0 +1,141 @@
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Barrier, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use arvan_test::{
+    coordinator::{Phase, RoundCoordinator},
+    Simulation, SimulationConfig,
+};
+
+const ROUNDS: u32 = 2000;
+const REPAIRERS: [u32; 4] = [2, 8, 16, 64];
+
+// the messages of the barrier rounds, a new set of barriers is sent with every phase
+enum Job {
+    Decide(Arc<Barrier>),
+    Execute(Arc<Barrier>, Arc<Barrier>),
+}
+
+// the rounds as they used to run, an mpsc receiver behind a mutex per repairer and three barriers per round
+fn barrier_rounds(repairers: u32) -> Duration {
+    let mut senders: Vec<Arc<Mutex<Sender<Job>>>> = Vec::new();
+    let mut handles = Vec::new();
+    for _ in 0..repairers {
+        let (sender, receiver) = channel::<Job>();
+        let receiver: Arc<Mutex<Receiver<Job>>> = Arc::new(Mutex::new(receiver));
+        senders.push(Arc::new(Mutex::new(sender)));
+        handles.push(thread::spawn(move || loop {
+            let job = match receiver.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+            match job {
+                Job::Decide(decided) => {
+                    decided.wait();
+                }
+                Job::Execute(beginning, ending) => {
+                    beginning.wait();
+                    ending.wait();
+                }
+            }
+        }));
+    }
+
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        let decided = Arc::new(Barrier::new(repairers as usize + 1));
+        let beginning = Arc::new(Barrier::new(repairers as usize + 1));
+        let ending = Arc::new(Barrier::new(repairers as usize + 1));
+        for sender in senders.iter() {
+            let _ = sender
+                .lock()
+                .unwrap()
+                .send(Job::Decide(Arc::clone(&decided)));
+        }
+        decided.wait();
+        for sender in senders.iter() {
+            let _ = sender
+                .lock()
+                .unwrap()
+                .send(Job::Execute(Arc::clone(&beginning), Arc::clone(&ending)));
+        }
+        beginning.wait();
+        ending.wait();
+    }
+    let elapsed = started.elapsed();
+
+    drop(senders);
+    for handle in handles {
+        let _ = handle.join();
+    }
+    elapsed
+}
+
+// the rounds of the coordinator, the phases go over crossbeam channels and come back as acks
+fn channel_rounds(repairers: u32) -> Duration {
+    let mut coordinator = RoundCoordinator::new(repairers);
+    let members = coordinator.take_members();
+    thread::scope(|scope| {
+        for member in members {
+            scope.spawn(move || {
+                while member.next_phase().is_some() {
+                    member.done();
+                }
+            });
+        }
+
+        let started = Instant::now();
+        for round in 0..ROUNDS {
+            coordinator.run_phase(Phase::Decide, round);
+            coordinator.run_phase(Phase::Execute, round);
+        }
+        let elapsed = started.elapsed();
+        coordinator.close();
+        elapsed
+    })
+}
+
+// the time a round of a whole run takes, the decisions and the executions included
+fn simulation_round(repairers: u32) -> Duration {
+    let result = Simulation::new(SimulationConfig {
+        rows: 64,
+        columns: 64,
+        total_broken: Some(512),
+        repairers,
+        seed: Some(42),
+        headless: true,
+        max_rounds: Some(ROUNDS),
+        ..Default::default()
+    })
+    .map(Simulation::run_to_completion)
+    .expect("the bench config is valid")
+    .expect("the bench repairers don't fail");
+    Duration::from_secs_f64(
+        (result.decide_ms + result.execute_ms) / 1000.0 / result.rounds.max(1) as f64,
+    )
+}
+
+fn per_round(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1_000_000.0 / ROUNDS as f64
+}
+
+fn main() {
+    println!("round latency over {} rounds", ROUNDS);
+    for repairers in REPAIRERS {
+        let barriers = per_round(barrier_rounds(repairers));
+        let channels = per_round(channel_rounds(repairers));
+        println!(
+            "repairers: {:>2}, barrier rounds: {:>8.2}µs, channel rounds: {:>8.2}µs, speedup: {:.2}x, simulation round: {:>8.2}µs",
+            repairers,
+            barriers,
+            channels,
+            barriers / channels,
+            simulation_round(repairers).as_secs_f64() * 1_000_000.0,
+        );
+    }
+}
@@ -0,0 +1,663 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+    ptr,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::{
+    grid::{Grid, Position},
+    models::{CellSet, CityMap, Note},
+    mods::{clear_terminal, print_grid},
+    simulation::{MAX_DAMAGE, MAX_REPAIRERS},
+};
+
+// the state of every cell of the city in a single flat array, kept on the heap or in a memory-mapped file
+// the layout is the same for both, so a state file can be watched by another process while the run goes on
+//
+// | header: 64 bytes | a location word per repairer | a record per cell, row by row |
+// the record of a cell is | fix claim: 8 bytes | work: 4 bytes, damage: 1 byte, blocked: 1 byte, 2 unused | a note word per repairer |
+pub struct Matrix {
+    grid: Grid,
+    repairers: u32,
+    base: *mut u8, // the start of the storage, every access goes through the atomics laid over it
+    storage: Storage,
+    payloads: Option<Box<[Payload]>>, // the maps published next to the notes, only when the maps are shared
+}
+
+// the map published next to a note
+type Payload = Mutex<Option<Arc<CityMap>>>;
+
+enum Storage {
+    Heap(#[allow(dead_code)] Vec<AtomicU64>), // only kept alive, it is reached through <base>
+    Mapped(MmapMut),
+}
+
+// SAFETY: the storage is only read and written through atomics and it lives as long as the matrix
+unsafe impl Send for Matrix {}
+unsafe impl Sync for Matrix {}
+
+// what the file of the state array starts with
+const MAGIC: [u8; 8] = *b"ARVNCITY";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 64;
+// the byte offsets of the header fields
+const VERSION_OFFSET: usize = 8;
+const ROWS_OFFSET: usize = 12;
+const COLUMNS_OFFSET: usize = 16;
+const REPAIRERS_OFFSET: usize = 20;
+const ROUND_OFFSET: usize = 24; // the rounds started so far, written by the master thread
+const FINISHED_OFFSET: usize = 28; // set once the run has ended
+const BROKEN_OFFSET: usize = 32; // the broken houses, counted by whoever breaks or restores one
+                                 // the byte offsets in the record of a cell
+const CLAIM_OFFSET: usize = 0;
+const WORK_OFFSET: usize = 8;
+const DAMAGE_OFFSET: usize = 12;
+const BLOCKED_OFFSET: usize = 13;
+const NOTES_OFFSET: usize = 16;
+
+// the size of the storage of a city, in 8 byte words, `None` when it doesn't fit in the address space
+fn storage_words(grid: Grid, repairers: u32) -> Option<usize> {
+    (grid.rows as usize)
+        .checked_mul(grid.columns as usize)?
+        .checked_mul(record_words(repairers))?
+        .checked_add(HEADER_SIZE / 8 + repairers as usize)
+        .filter(|words| words.checked_mul(8).is_some())
+}
+
+// the index of a cell of the grid, row by row
+fn cell_index(grid: Grid, position: Position) -> usize {
+    position.row as usize * grid.columns as usize + position.col as usize
+}
+
+// the byte offset of the record of a cell of the grid, the grid was sized with `storage_words`
+fn record_offset(grid: Grid, repairers: u32, position: Position) -> usize {
+    HEADER_SIZE + repairers as usize * 8 + cell_index(grid, position) * record_words(repairers) * 8
+}
+
+fn record_words(repairers: u32) -> usize {
+    NOTES_OFFSET / 8 + repairers as usize
+}
+
+impl Matrix {
+    // the state array on the heap, an error instead of an abort when there isn't enough memory for it
+    pub fn new(grid: Grid, repairers: u32, share_maps: bool) -> io::Result<Self> {
+        let words = storage_words(grid, repairers).ok_or_else(too_large)?;
+        let mut storage: Vec<AtomicU64> = Vec::new();
+        storage.try_reserve_exact(words).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                format!("the state of the city needs {} bytes", words * 8),
+            )
+        })?;
+        storage.extend((0..words).map(|_| AtomicU64::new(0)));
+        let base = storage.as_mut_ptr() as *mut u8;
+        Ok(Self::init(
+            grid,
+            repairers,
+            share_maps,
+            base,
+            Storage::Heap(storage),
+        ))
+    }
+
+    // the state array in a memory-mapped file, the file is created or truncated to the size of the city
+    pub fn map(
+        path: impl AsRef<Path>,
+        grid: Grid,
+        repairers: u32,
+        share_maps: bool,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let words = storage_words(grid, repairers).ok_or_else(too_large)?;
+        file.set_len((words * 8) as u64)?;
+        // SAFETY: the file was just truncated and sized for this run, it is only changed through the atomics from now on
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        let base = mmap.as_mut_ptr();
+        Ok(Self::init(
+            grid,
+            repairers,
+            share_maps,
+            base,
+            Storage::Mapped(mmap),
+        ))
+    }
+
+    fn init(grid: Grid, repairers: u32, share_maps: bool, base: *mut u8, storage: Storage) -> Self {
+        let matrix = Self {
+            grid,
+            repairers,
+            base,
+            storage,
+            payloads: share_maps.then(|| {
+                (0..grid.rows as usize * grid.columns as usize * repairers as usize)
+                    .map(|_| Mutex::new(None))
+                    .collect()
+            }),
+        };
+        // SAFETY: the header is written before the matrix is shared with anyone, the magic last so a watcher never reads half of it
+        unsafe {
+            for (offset, value) in [
+                (VERSION_OFFSET, VERSION),
+                (ROWS_OFFSET, grid.rows),
+                (COLUMNS_OFFSET, grid.columns),
+                (REPAIRERS_OFFSET, repairers),
+            ] {
+                matrix.word32(offset).store(value, Ordering::Relaxed);
+            }
+            ptr::copy_nonoverlapping(MAGIC.as_ptr(), base, MAGIC.len());
+        }
+        matrix
+    }
+
+    // the state array of a running city, mapped by a repairer process next to the master one
+    // the maps of the notes stay in the process that published them
+    pub fn attach(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        // SAFETY: the file is shared with the other processes of the run, it is only changed through the atomics
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        let (grid, repairers) = read_header(&mmap)?;
+        let base = mmap.as_mut_ptr();
+        Ok(Self {
+            grid,
+            repairers,
+            base,
+            storage: Storage::Mapped(mmap),
+            payloads: None,
+        })
+    }
+
+    // SAFETY: the offset must be aligned and within the storage
+    unsafe fn word32(&self, offset: usize) -> &AtomicU32 {
+        AtomicU32::from_ptr(self.base.add(offset) as *mut u32)
+    }
+
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    pub fn repairers(&self) -> u32 {
+        self.repairers
+    }
+
+    pub fn house(&self, position: Position) -> House<'_> {
+        assert!(
+            self.grid.contains(position),
+            "{} is outside of the grid",
+            position
+        );
+        House {
+            matrix: self,
+            index: cell_index(self.grid, position),
+            // SAFETY: the storage was sized with `storage_words`, the record of a cell of the grid is within it
+            record: unsafe {
+                self.base
+                    .add(record_offset(self.grid, self.repairers, position))
+            },
+        }
+    }
+
+    // a snapshot of the damage of every house, row by row
+    pub fn damages(&self) -> Vec<Vec<u8>> {
+        (0..self.grid.rows)
+            .map(|row| {
+                (0..self.grid.columns)
+                    .map(|col| {
+                        self.house(Position::new(row, col))
+                            .damage()
+                            .load(Ordering::Relaxed)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // adds <damage> to a house, returns whether a fine house broke
+    pub fn break_house(&self, position: Position, damage: u8) -> bool {
+        let before = self
+            .house(position)
+            .damage()
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |before| {
+                Some(before.saturating_add(damage).min(MAX_DAMAGE))
+            })
+            .unwrap();
+        let broke = before == 0 && damage > 0;
+        if broke {
+            self.broken_counter().fetch_add(1, Ordering::AcqRel);
+        }
+        broke
+    }
+
+    // the broken houses of the whole city, kept up to date instead of counted
+    pub fn broken_houses(&self) -> u32 {
+        self.broken_counter().load(Ordering::Acquire)
+    }
+
+    fn broken_counter(&self) -> &AtomicU32 {
+        // SAFETY: the header field is aligned and within the storage
+        unsafe { self.word32(BROKEN_OFFSET) }
+    }
+
+    pub fn set_round(&self, round: u32) {
+        // SAFETY: the header field is aligned and within the storage
+        unsafe { self.word32(ROUND_OFFSET) }.store(round, Ordering::Release);
+    }
+
+    pub fn set_finished(&self) {
+        // SAFETY: the header field is aligned and within the storage
+        unsafe { self.word32(FINISHED_OFFSET) }.store(1, Ordering::Release);
+    }
+
+    pub fn set_location(&self, id: u32, position: Position) {
+        assert!(id < self.repairers, "unknown repairer {}", id);
+        // SAFETY: the location word of a known repairer is aligned and within the storage
+        let word = unsafe {
+            AtomicU64::from_ptr(self.base.add(HEADER_SIZE + id as usize * 8) as *mut u64)
+        };
+        word.store(pack_location(position), Ordering::Release);
+    }
+
+    // writes the changes of a mapped state array to its file, the watchers see them without it
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.storage {
+            Storage::Heap(_) => Ok(()),
+            Storage::Mapped(mmap) => mmap.flush(),
+        }
+    }
+}
+
+impl fmt::Debug for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Matrix")
+            .field("grid", &self.grid)
+            .field("repairers", &self.repairers)
+            .field("mapped", &matches!(self.storage, Storage::Mapped(_)))
+            .finish()
+    }
+}
+
+// the record of a single house in the state array
+#[derive(Clone, Copy)]
+pub struct House<'a> {
+    matrix: &'a Matrix,
+    index: usize,
+    record: *mut u8,
+}
+
+impl<'a> House<'a> {
+    // the id and round of the repairer applying the pooled work of the round, see `fix_claim`
+    pub fn claim(&self) -> &'a AtomicU64 {
+        // SAFETY: the field is aligned and within the record
+        unsafe { AtomicU64::from_ptr(self.record.add(CLAIM_OFFSET) as *mut u64) }
+    }
+
+    // the repair work pledged in the round
+    pub fn work(&self) -> &'a AtomicU32 {
+        // SAFETY: the field is aligned and within the record
+        unsafe { AtomicU32::from_ptr(self.record.add(WORK_OFFSET) as *mut u32) }
+    }
+
+    // takes <work> off the damage of the house, returns the damage before and after it
+    // the house is counted as restored by the single repair that brings it to 0
+    pub fn repair(&self, work: u32) -> (u8, u8) {
+        let before = self
+            .damage()
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |damage| {
+                Some((damage as u32).saturating_sub(work) as u8)
+            })
+            .unwrap();
+        let left = (before as u32).saturating_sub(work) as u8;
+        if before > 0 && left == 0 {
+            self.matrix.broken_counter().fetch_sub(1, Ordering::AcqRel);
+        }
+        (before, left)
+    }
+
+    // 0 for a fine house, up to `MAX_DAMAGE` for a fully broken one
+    pub fn damage(&self) -> &'a AtomicU8 {
+        // SAFETY: the field is within the record
+        unsafe { AtomicU8::from_ptr(self.record.add(DAMAGE_OFFSET)) }
+    }
+
+    // marks the rubble for the watchers, the repairers know the blocked cells from the scenario
+    pub fn block(&self) {
+        // SAFETY: the field is within the record
+        unsafe { AtomicU8::from_ptr(self.record.add(BLOCKED_OFFSET)) }.store(1, Ordering::Relaxed);
+    }
+
+    pub fn note(&self, id: u32) -> NoteSlot<'a> {
+        assert!(id < self.matrix.repairers, "unknown repairer {}", id);
+        NoteSlot {
+            // SAFETY: the note word of a known repairer is aligned and within the record
+            note: unsafe {
+                AtomicU64::from_ptr(self.record.add(NOTES_OFFSET + id as usize * 8) as *mut u64)
+            },
+            payload: self.matrix.payloads.as_ref().map(|payloads| {
+                &payloads[self.index * self.matrix.repairers as usize + id as usize]
+            }),
+        }
+    }
+
+    // the notes of every repairer, the unwritten ones included
+    pub fn notes(&self) -> impl Iterator<Item = Note> + 'a {
+        let house = *self;
+        (0..self.matrix.repairers).map(move |id| house.note(id).load(id))
+    }
+}
+
+// the storage of a single note, the record itself is atomic and readers never block the writer
+// the optional map is published before the record that flags it
+pub struct NoteSlot<'a> {
+    note: &'a AtomicU64,
+    payload: Option<&'a Payload>,
+}
+
+impl NoteSlot<'_> {
+    pub fn load(&self, id: u32) -> Note {
+        Note::unpack(id, self.note.load(Ordering::Acquire))
+    }
+
+    pub fn store(&self, note: &Note) {
+        self.note.store(note.pack(), Ordering::Release);
+    }
+
+    pub fn load_payload(&self) -> Option<Arc<CityMap>> {
+        self.payload
+            .and_then(|payload| payload.lock().unwrap().clone())
+    }
+
+    // the note goes out without the map when the maps aren't shared in this run
+    pub fn publish(&self, note: &Note, payload: Arc<CityMap>) {
+        let Some(slot) = self.payload else {
+            return self.store(note);
+        };
+        *slot.lock().unwrap() = Some(payload);
+        self.store(&Note {
+            flags: note.flags | Note::PAYLOAD,
+            ..*note
+        });
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "the city is too large")
+}
+
+// the grid and the amount of repairers of a state file, the header isn't changed once the magic is written
+fn read_header(bytes: &[u8]) -> io::Result<(Grid, u32)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    if bytes.len() < HEADER_SIZE || bytes[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not a city state file"));
+    }
+    let field = |offset: usize| u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    if field(VERSION_OFFSET) != VERSION {
+        return Err(invalid("unsupported city state file version"));
+    }
+    let grid = Grid::new(field(ROWS_OFFSET), field(COLUMNS_OFFSET));
+    let repairers = field(REPAIRERS_OFFSET);
+    if repairers > MAX_REPAIRERS {
+        return Err(invalid("too many repairers in the city state file"));
+    }
+    match storage_words(grid, repairers) {
+        None => Err(invalid("the city of the state file is too large")),
+        Some(words) if bytes.len() < words * 8 => Err(invalid("the city state file is truncated")),
+        Some(_) => Ok((grid, repairers)),
+    }
+}
+
+// the row in the high half and the column in the low half, a repairer that hasn't been placed yet reads as the first cell
+fn pack_location(position: Position) -> u64 {
+    ((position.row as u64) << 32) | position.col as u64
+}
+
+fn unpack_location(raw: u64) -> Position {
+    Position::new((raw >> 32) as u32, raw as u32)
+}
+
+// a read-only look at the state file of a run, it can be opened by another process while the run is going on
+pub struct CityView {
+    grid: Grid,
+    repairers: u32,
+    mmap: Mmap,
+}
+
+impl CityView {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file is only read, the volatile reads tolerate the run changing it underneath
+        let mmap = unsafe { Mmap::map(&file)? };
+        let (grid, repairers) = read_header(&mmap)?;
+        Ok(Self {
+            grid,
+            repairers,
+            mmap,
+        })
+    }
+
+    // the writers only use aligned atomic stores, an aligned read of the same size never sees half of one
+    fn read_u32(&self, offset: usize) -> u32 {
+        // SAFETY: every offset read is aligned and was checked against the length of the file
+        unsafe { ptr::read_volatile(self.mmap.as_ptr().add(offset) as *const u32) }
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        // SAFETY: every offset read is aligned and was checked against the length of the file
+        unsafe { ptr::read_volatile(self.mmap.as_ptr().add(offset) as *const u64) }
+    }
+
+    fn read_u8(&self, offset: usize) -> u8 {
+        // SAFETY: every offset read was checked against the length of the file
+        unsafe { ptr::read_volatile(self.mmap.as_ptr().add(offset)) }
+    }
+
+    fn record(&self, position: Position) -> usize {
+        assert!(
+            self.grid.contains(position),
+            "{} is outside of the grid",
+            position
+        );
+        record_offset(self.grid, self.repairers, position)
+    }
+
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    pub fn repairers(&self) -> u32 {
+        self.repairers
+    }
+
+    pub fn round(&self) -> u32 {
+        self.read_u32(ROUND_OFFSET)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.read_u32(FINISHED_OFFSET) != 0
+    }
+
+    pub fn damage(&self, position: Position) -> u8 {
+        self.read_u8(self.record(position) + DAMAGE_OFFSET)
+    }
+
+    pub fn is_blocked(&self, position: Position) -> bool {
+        self.read_u8(self.record(position) + BLOCKED_OFFSET) != 0
+    }
+
+    pub fn note(&self, position: Position, id: u32) -> Note {
+        assert!(id < self.repairers, "unknown repairer {}", id);
+        Note::unpack(
+            id,
+            self.read_u64(self.record(position) + NOTES_OFFSET + id as usize * 8),
+        )
+    }
+
+    pub fn location(&self, id: u32) -> Position {
+        assert!(id < self.repairers, "unknown repairer {}", id);
+        unpack_location(self.read_u64(HEADER_SIZE + id as usize * 8))
+    }
+
+    // a snapshot of the damage of every house, row by row
+    pub fn damages(&self) -> Vec<Vec<u8>> {
+        (0..self.grid.rows)
+            .map(|row| {
+                (0..self.grid.columns)
+                    .map(|col| self.damage(Position::new(row, col)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // renders the city every <interval> until the run has ended
+    pub fn watch(&self, interval: Duration) {
+        let mut blocked = CellSet::new(self.grid.rows, self.grid.columns);
+        for position in self.grid.positions().filter(|p| self.is_blocked(*p)) {
+            blocked.insert(position.into());
+        }
+        loop {
+            // read before the frame so the last frame is always the final state
+            let finished = self.is_finished();
+            let locations: Vec<Position> =
+                (0..self.repairers).map(|id| self.location(id)).collect();
+            clear_terminal();
+            println!(
+                "round {}{}",
+                self.round(),
+                if finished { ", finished" } else { "" }
+            );
+            print_grid(&self.damages(), &blocked, &locations);
+            if finished {
+                break;
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::*;
+    use crate::models::Move;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("arvan_test-{}-{}.state", process::id(), name))
+    }
+
+    // the whole storage of a matrix, header included
+    fn bytes(matrix: &Matrix) -> &[u8] {
+        let words = storage_words(matrix.grid, matrix.repairers).unwrap();
+        // SAFETY: the storage was sized with `storage_words`
+        unsafe { std::slice::from_raw_parts(matrix.base, words * 8) }
+    }
+
+    // the same writes on both matrices
+    fn fill(matrix: &Matrix) {
+        let house = matrix.house(Position::new(2, 3));
+        house
+            .claim()
+            .store(0x0102_0304_0506_0708, Ordering::Relaxed);
+        house.work().store(17, Ordering::Relaxed);
+        house.damage().store(9, Ordering::Relaxed);
+        house.block();
+        house
+            .note(1)
+            .store(&Note::new(1, 4, 12).with_heading(&Move::Left));
+        matrix.set_location(2, Position::new(3, 4));
+        matrix.set_round(12);
+        matrix.set_finished();
+    }
+
+    fn header(rows: u32, columns: u32, repairers: u32) -> Vec<u8> {
+        let mut bytes = vec![0; 128];
+        bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        for (offset, value) in [
+            (VERSION_OFFSET, VERSION),
+            (ROWS_OFFSET, rows),
+            (COLUMNS_OFFSET, columns),
+            (REPAIRERS_OFFSET, repairers),
+        ] {
+            bytes[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn heap_and_mapped_matrices_share_the_layout() {
+        let grid = Grid::new(4, 5);
+        let path = temp_path("layout");
+        let heap = Matrix::new(grid, 3, false).unwrap();
+        let mapped = Matrix::map(&path, grid, 3, false).unwrap();
+        fill(&heap);
+        fill(&mapped);
+        assert_eq!(bytes(&heap), bytes(&mapped));
+
+        mapped.flush().unwrap();
+        let view = CityView::open(&path).unwrap();
+        assert_eq!(view.grid(), grid);
+        assert_eq!(view.round(), 12);
+        assert!(view.is_finished());
+        assert_eq!(view.damage(Position::new(2, 3)), 9);
+        assert!(view.is_blocked(Position::new(2, 3)));
+        assert!(!view.is_blocked(Position::new(3, 2)));
+        assert_eq!(
+            view.note(Position::new(2, 3), 1),
+            Note::new(1, 4, 12).with_heading(&Move::Left)
+        );
+        assert_eq!(view.location(2), Position::new(3, 4));
+
+        let attached = Matrix::attach(&path).unwrap();
+        assert_eq!(bytes(&attached), bytes(&heap));
+        drop((view, attached, mapped));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn the_broken_houses_are_counted() {
+        let matrix = Matrix::new(Grid::new(3, 3), 1, false).unwrap();
+        assert!(matrix.break_house(Position::new(0, 0), 4));
+        assert!(matrix.break_house(Position::new(1, 1), MAX_DAMAGE));
+        // already broken, it only gets worse
+        assert!(!matrix.break_house(Position::new(0, 0), MAX_DAMAGE));
+        assert_eq!(matrix.broken_houses(), 2);
+
+        let house = matrix.house(Position::new(0, 0));
+        assert_eq!(house.repair(3), (MAX_DAMAGE, MAX_DAMAGE - 3));
+        assert_eq!(matrix.broken_houses(), 2);
+        assert_eq!(house.repair(MAX_DAMAGE as u32), (MAX_DAMAGE - 3, 0));
+        assert_eq!(house.repair(1), (0, 0));
+        assert_eq!(matrix.broken_houses(), 1);
+    }
+
+    #[test]
+    fn read_header_rejects_broken_files() {
+        let matrix = Matrix::new(Grid::new(3, 3), 2, false).unwrap();
+        assert_eq!(read_header(bytes(&matrix)).unwrap(), (Grid::new(3, 3), 2));
+
+        // sized for a 3x3 city of 2 repairers minus its last word
+        let truncated = &bytes(&matrix)[..bytes(&matrix).len() - 8];
+        assert!(read_header(truncated).is_err());
+        // the cell count overflows a u32
+        assert!(read_header(&header(65536, 65536, 1)).is_err());
+        assert!(read_header(&header(u32::MAX, u32::MAX, 4)).is_err());
+        assert!(read_header(&header(2, 2, MAX_REPAIRERS + 1)).is_err());
+        assert!(read_header(&header(2, 2, 1)[..HEADER_SIZE - 1]).is_err());
+        let mut magic = header(0, 0, 0);
+        magic[0] = b'X';
+        assert!(read_header(&magic).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{path::PathBuf, time::Duration};
+
+use arvan_test::{
+    models::{MovementAlgorithm, NoteDefense},
+    scenario::ScenarioError,
+    simulation::{ExecutionMode, FailurePolicy, INIT_ALGOS, MAX_DAMAGE},
+    Scenario, SimulationConfig, StrategyFactory,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "simulates a team of repairers fixing the broken houses of a city"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// runs a single simulation and renders every round
+    Run {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+        /// the seed of the run, a random one is chosen when missing
+        #[arg(long)]
+        seed: Option<u64>,
+        /// saves the scenario of the run, including its seed, to this file
+        #[arg(long)]
+        save_scenario: Option<PathBuf>,
+        /// skips the rendering and the sleeps and only prints the results
+        #[arg(long)]
+        headless: bool,
+        /// records every decision and execution of the run to this file
+        #[arg(long)]
+        trace: Option<PathBuf>,
+        /// places the state of the cells in this memory-mapped file instead of the heap, `watch` can follow it live
+        #[arg(long)]
+        state_file: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// runs many headless simulations back to back and reports the rounds and the wall time
+    Bench {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+        /// the amount of runs
+        #[arg(long, default_value_t = 10)]
+        runs: u32,
+        /// the seed of the first run, the next runs use the following seeds
+        #[arg(long)]
+        seed: Option<u64>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// renders a recorded run frame by frame from its trace file
+    Replay {
+        /// the trace file written by `run --trace`
+        trace: PathBuf,
+        /// the sleep between the rendered rounds in milliseconds
+        #[arg(long, default_value_t = 50)]
+        frame_delay: u64,
+    },
+    /// renders the state file of a running simulation until the run ends, without touching it
+    Watch {
+        /// the state file written by `run --state-file`
+        state_file: PathBuf,
+        /// the sleep between the rendered frames in milliseconds
+        #[arg(long, default_value_t = 100)]
+        interval: u64,
+    },
+    /// the body of a repairer process in the `processes` mode, driven by the master process on its input and output
+    #[command(hide = true)]
+    Worker,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    /// the format of the results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+    /// writes the results to this file instead of the standard output
+    #[arg(long)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ScenarioArgs {
+    /// loads the grid, the broken houses and the repairers from a scenario file instead of generating them
+    #[arg(long)]
+    pub scenario: Option<PathBuf>,
+    /// the amount of rows of the city
+    #[arg(long, default_value_t = 7)]
+    pub rows: u32,
+    /// the amount of columns of the city
+    #[arg(long, default_value_t = 7)]
+    pub columns: u32,
+    /// the exact amount of broken houses, otherwise a random amount between the min and max is chosen
+    #[arg(long)]
+    pub broken: Option<u32>,
+    /// the minimum amount of random broken houses
+    #[arg(long, default_value_t = 3)]
+    pub min_broken: u32,
+    /// the maximum amount of random broken houses
+    #[arg(long, default_value_t = 6)]
+    pub max_broken: u32,
+    /// the minimum damage of the random broken houses
+    #[arg(long, default_value_t = MAX_DAMAGE)]
+    pub min_damage: u8,
+    /// the maximum damage of the random broken houses
+    #[arg(long, default_value_t = MAX_DAMAGE)]
+    pub max_damage: u8,
+    /// the damage a repairer removes with a single fix
+    #[arg(long, default_value_t = MAX_DAMAGE)]
+    pub repair_power: u8,
+    /// the amount of repairers
+    #[arg(long, default_value_t = 4)]
+    pub repairers: u32,
+    /// the initial movement algorithms, repeated when there is more repairers than algorithms
+    #[arg(long, value_delimiter = ',')]
+    pub algorithms: Vec<MovementAlgorithm>,
+    /// the sleep between the rendered rounds in milliseconds
+    #[arg(long, default_value_t = 50)]
+    pub frame_delay: u64,
+    /// leaves the known map of the city next to every note so the repairers share what they have seen
+    #[arg(long)]
+    pub share_maps: bool,
+    /// the chance of a random house breaking in each round, the runs with aftershocks need a termination limit
+    #[arg(long, default_value_t = 0.0)]
+    pub aftershock_rate: f64,
+    /// stops the run after this many rounds
+    #[arg(long)]
+    pub max_rounds: Option<u32>,
+    /// stops the run once the rounds took this many milliseconds
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+    /// stops the run once the city stayed fully repaired for this many rounds
+    #[arg(long)]
+    pub quiescence: Option<u32>,
+    /// what to do when a repairer fails, `abort` stops the run with an error and `continue` carries on with the survivors
+    #[arg(long, default_value_t = FailurePolicy::Abort)]
+    pub on_failure: FailurePolicy,
+    /// the amount of byzantine repairers, the first ones write false repair counts in their notes
+    #[arg(long, default_value_t = 0)]
+    pub liars: u32,
+    /// the chance of any written note carrying a random repair count
+    #[arg(long, default_value_t = 0.0)]
+    pub note_noise: f64,
+    /// how the repairers check the counts they read, `trust` takes every increase and `bounded` only the ones possible in the elapsed rounds
+    #[arg(long, default_value_t = NoteDefense::Trust)]
+    pub note_defense: NoteDefense,
+    /// `lockstep` runs the repairers in shared rounds, `free-running` lets each of them decide and execute on its own clock,
+    /// `processes` runs the shared rounds with every repairer in its own process
+    #[arg(long, default_value_t = ExecutionMode::Lockstep)]
+    pub mode: ExecutionMode,
+}
+
+impl ScenarioArgs {
+    pub fn to_config(&self, seed: Option<u64>) -> Result<SimulationConfig, ScenarioError> {
+        Ok(SimulationConfig {
+            rows: self.rows,
+            columns: self.columns,
+            total_broken: self.broken,
+            broken_range: (self.min_broken, self.max_broken),
+            damage_range: (self.min_damage, self.max_damage),
+            repair_power: self.repair_power,
+            repairers: self.repairers,
+            algorithms: if self.algorithms.is_empty() {
+                INIT_ALGOS.to_vec()
+            } else {
+                self.algorithms.clone()
+            },
+            seed,
+            frame_delay: Duration::from_millis(self.frame_delay),
+            headless: false,
+            trace: false,
+            scenario: match &self.scenario {
+                Some(path) => Some(Scenario::load(path)?),
+                None => None,
+            },
+            strategy: StrategyFactory::default(),
+            share_maps: self.share_maps,
+            aftershock_rate: self.aftershock_rate,
+            max_rounds: self.max_rounds,
+            time_limit: self.time_limit.map(Duration::from_millis),
+            quiescence: self.quiescence,
+            on_failure: self.on_failure,
+            liars: self.liars,
+            note_noise: self.note_noise,
+            note_defense: self.note_defense,
+            mode: self.mode,
+            state_file: None,
+            worker_program: None,
+        })
+    }
+}
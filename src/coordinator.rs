@@ -0,0 +1,339 @@
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{self, select, Receiver, Sender, TryRecvError};
+
+// the two halves of a lockstep round
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Decide,
+    Execute,
+}
+
+// the time the master thread has waited on each phase, summed over the rounds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PhaseTimings {
+    pub decide: Duration,
+    pub execute: Duration,
+}
+
+// what a run can be told from outside of its rounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    Pause,  // the repairers take no phase or step until they are resumed
+    Resume, // the paused repairers carry on
+    Abort,  // the repairers leave their loops and the run ends
+}
+
+#[derive(Debug)]
+enum Ack {
+    Done,      // done with the open phase
+    Left(u32), // done with the open phase and out of the next ones
+}
+
+// the pause and abort switches of a run, it can be cloned to another thread
+#[derive(Clone, Debug)]
+pub struct Controller {
+    repairers: Vec<Sender<Control>>,
+    master: Sender<Control>,
+}
+
+impl Controller {
+    pub fn pause(&self) {
+        self.tell_repairers(Control::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.tell_repairers(Control::Resume);
+    }
+
+    pub fn abort(&self) {
+        let _ = self.master.send(Control::Abort);
+        self.tell_repairers(Control::Abort);
+    }
+
+    // the repairer threads that have already left don't listen anymore
+    fn tell_repairers(&self, control: Control) {
+        for repairer in self.repairers.iter() {
+            let _ = repairer.send(control);
+        }
+    }
+}
+
+// the end of the round channels held by a repairer thread, each of them has a single consumer
+#[derive(Debug)]
+pub struct RoundMember {
+    pub id: u32,
+    phases: Receiver<(Phase, u32)>,
+    control: Receiver<Control>,
+    acks: Sender<Ack>,
+}
+
+impl RoundMember {
+    // blocks until the master thread opens the next phase, `None` once the repairer is out of the run
+    pub fn next_phase(&self) -> Option<(Phase, u32)> {
+        loop {
+            select! {
+                recv(self.control) -> control => match control {
+                    Ok(Control::Pause) if self.wait_resume() => {}
+                    Ok(Control::Resume) => {}
+                    _ => return None,
+                },
+                recv(self.phases) -> phase => return phase.ok(),
+            }
+        }
+    }
+
+    // checked by a free running repairer between its steps, false once it has to leave
+    pub fn keep_running(&self) -> bool {
+        match self.control.try_recv() {
+            Ok(Control::Pause) => self.wait_resume(),
+            Ok(Control::Resume) | Err(TryRecvError::Empty) => true,
+            Ok(Control::Abort) | Err(TryRecvError::Disconnected) => false,
+        }
+    }
+
+    // a paused repairer only listens to the control messages, false when it is aborted instead of resumed
+    fn wait_resume(&self) -> bool {
+        loop {
+            match self.control.recv() {
+                Ok(Control::Resume) => return true,
+                Ok(Control::Pause) => {}
+                Ok(Control::Abort) | Err(_) => return false,
+            }
+        }
+    }
+
+    // the repairer is done with the open phase
+    pub fn done(&self) {
+        let _ = self.acks.send(Ack::Done);
+    }
+
+    // the repairer is done with the open phase and out of the next ones
+    pub fn leave(&self) {
+        let _ = self.acks.send(Ack::Left(self.id));
+    }
+}
+
+// drives the lockstep rounds, built once for the whole run instead of a set of barriers per round
+// the master thread opens the phases for the live repairers only and waits on their acks, a repairer leaving the run is taken out of the next phases
+#[derive(Debug)]
+pub struct RoundCoordinator {
+    phases: Vec<Option<Sender<(Phase, u32)>>>, // `None` once the repairer is out of the run
+    acks: Receiver<Ack>,
+    master: Receiver<Control>,
+    controller: Controller,
+    members: Vec<RoundMember>, // handed to the repairer threads when the run starts
+    aborted: bool,
+    timings: PhaseTimings,
+}
+
+impl RoundCoordinator {
+    pub fn new(repairers: u32) -> Self {
+        let (acks_sender, acks) = channel::unbounded();
+        let (master_sender, master) = channel::unbounded();
+        let mut phases = Vec::new();
+        let mut controls = Vec::new();
+        let mut members = Vec::new();
+        for id in 0..repairers {
+            let (phase_sender, phase_receiver) = channel::unbounded();
+            let (control_sender, control_receiver) = channel::unbounded();
+            phases.push(Some(phase_sender));
+            controls.push(control_sender);
+            members.push(RoundMember {
+                id,
+                phases: phase_receiver,
+                control: control_receiver,
+                acks: acks_sender.clone(),
+            });
+        }
+
+        Self {
+            phases,
+            acks,
+            master,
+            controller: Controller {
+                repairers: controls,
+                master: master_sender,
+            },
+            members,
+            aborted: false,
+            timings: PhaseTimings::default(),
+        }
+    }
+
+    pub fn take_members(&mut self) -> Vec<RoundMember> {
+        std::mem::take(&mut self.members)
+    }
+
+    pub fn controller(&self) -> Controller {
+        self.controller.clone()
+    }
+
+    // opens the phase for every live repairer and blocks until all of them are done with it or have left
+    // returns `None` when the run is aborted in the middle of the phase
+    pub fn run_phase(&mut self, phase: Phase, round: u32) -> Option<Duration> {
+        let started = Instant::now();
+        let mut pending: u32 = 0;
+        for sender in self.phases.iter_mut() {
+            // a repairer thread that has already ended has dropped its receiver
+            if sender
+                .as_ref()
+                .is_some_and(|s| s.send((phase, round)).is_ok())
+            {
+                pending += 1;
+            } else {
+                *sender = None;
+            }
+        }
+        while pending > 0 {
+            select! {
+                recv(self.acks) -> ack => match ack {
+                    Ok(Ack::Done) => pending -= 1,
+                    Ok(Ack::Left(id)) => {
+                        self.phases[id as usize] = None;
+                        pending -= 1;
+                    }
+                    Err(_) => break,
+                },
+                recv(self.master) -> control => if let Ok(Control::Abort) = control {
+                    self.aborted = true;
+                    return None;
+                },
+            }
+        }
+
+        let elapsed = started.elapsed();
+        match phase {
+            Phase::Decide => self.timings.decide += elapsed,
+            Phase::Execute => self.timings.execute += elapsed,
+        }
+        Some(elapsed)
+    }
+
+    // whether the run has been aborted through the controller
+    pub fn aborted(&mut self) -> bool {
+        while let Ok(control) = self.master.try_recv() {
+            self.aborted |= control == Control::Abort;
+        }
+        self.aborted
+    }
+
+    // takes a repairer out of the next phases, its thread leaves once its phase channel is closed
+    pub fn dismiss(&mut self, id: u32) {
+        self.phases[id as usize] = None;
+    }
+
+    // ends the rounds, every repairer leaves its loop
+    pub fn close(&mut self) {
+        for sender in self.phases.iter_mut() {
+            *sender = None;
+        }
+        self.controller.tell_repairers(Control::Abort);
+    }
+
+    pub fn timings(&self) -> PhaseTimings {
+        self.timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    // takes part in the phases until its round to leave, the last phase it is in is the execution of that round
+    fn member_leaving_at(member: RoundMember, leave_round: u32) -> u32 {
+        let mut phases = 0;
+        while let Some((phase, round)) = member.next_phase() {
+            phases += 1;
+            match phase == Phase::Execute && round == leave_round {
+                true => {
+                    member.leave();
+                    break;
+                }
+                false => member.done(),
+            }
+        }
+        phases
+    }
+
+    fn live(coordinator: &RoundCoordinator) -> usize {
+        coordinator.phases.iter().flatten().count()
+    }
+
+    #[test]
+    fn the_phases_go_on_as_the_members_leave() {
+        let mut coordinator = RoundCoordinator::new(4);
+        let members = coordinator.take_members();
+        thread::scope(|scope| {
+            let repairers: Vec<_> = members
+                .into_iter()
+                .map(|member| {
+                    let leave_round = member.id;
+                    scope.spawn(move || member_leaving_at(member, leave_round))
+                })
+                .collect();
+            for round in 0..4 {
+                assert!(coordinator.run_phase(Phase::Decide, round).is_some());
+                assert!(coordinator.run_phase(Phase::Execute, round).is_some());
+                assert_eq!(live(&coordinator), 3 - round as usize);
+            }
+            // every member has left, there is no one to wait on
+            assert!(coordinator.run_phase(Phase::Decide, 4).is_some());
+            let phases: Vec<u32> = repairers.into_iter().map(|r| r.join().unwrap()).collect();
+            assert_eq!(phases, vec![2, 4, 6, 8]);
+        });
+        assert!(!coordinator.aborted());
+    }
+
+    #[test]
+    fn the_dismissed_and_the_ended_members_are_not_waited_on() {
+        let mut coordinator = RoundCoordinator::new(3);
+        let mut members = coordinator.take_members();
+        // the thread of the last member has ended without leaving, its phase channel is closed
+        drop(members.pop());
+        thread::scope(|scope| {
+            let repairers: Vec<_> = members
+                .into_iter()
+                .map(|member| scope.spawn(move || member_leaving_at(member, u32::MAX)))
+                .collect();
+            assert!(coordinator.run_phase(Phase::Decide, 0).is_some());
+            assert_eq!(live(&coordinator), 2);
+            // the dismissed member finds its phase channel closed and leaves its loop
+            coordinator.dismiss(0);
+            assert!(coordinator.run_phase(Phase::Execute, 0).is_some());
+            assert!(coordinator.run_phase(Phase::Decide, 1).is_some());
+            assert_eq!(live(&coordinator), 1);
+            coordinator.close();
+            let phases: Vec<u32> = repairers.into_iter().map(|r| r.join().unwrap()).collect();
+            assert_eq!(phases, vec![1, 3]);
+        });
+    }
+
+    #[test]
+    fn an_abort_ends_a_pending_phase() {
+        let mut coordinator = RoundCoordinator::new(2);
+        let members = coordinator.take_members();
+        let controller = coordinator.controller();
+        thread::scope(|scope| {
+            // the first member is done with the phase, the second one is stuck in it
+            let (done, stuck) = (&members[0], &members[1]);
+            let repairer = scope.spawn(move || {
+                let phase = done.next_phase();
+                done.done();
+                phase
+            });
+            scope.spawn(move || {
+                assert_eq!(stuck.next_phase(), Some((Phase::Decide, 0)));
+                controller.abort();
+            });
+            assert_eq!(coordinator.run_phase(Phase::Decide, 0), None);
+            assert_eq!(repairer.join().unwrap(), Some((Phase::Decide, 0)));
+        });
+        assert!(coordinator.aborted());
+        // the repairers are told to leave as well, in the lockstep and in the free running loops
+        assert_eq!(members[0].next_phase(), None);
+        assert!(!members[1].keep_running());
+        assert_eq!(coordinator.timings(), PhaseTimings::default());
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{ConfigError, Move};
+
+// a cell of the city, written as `[row, col]` in the scenario and the trace files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "(u32, u32)", into = "(u32, u32)")]
+pub struct Position {
+    pub row: u32,
+    pub col: u32,
+}
+
+impl Position {
+    pub fn new(row: u32, col: u32) -> Self {
+        Self { row, col }
+    }
+}
+
+impl From<(u32, u32)> for Position {
+    fn from((row, col): (u32, u32)) -> Self {
+        Self { row, col }
+    }
+}
+
+impl From<Position> for (u32, u32) {
+    fn from(position: Position) -> Self {
+        (position.row, position.col)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
+    }
+}
+
+// the bounds of the city, every location is computed through it so a move can never leave the grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+impl Grid {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self { rows, columns }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        position.row < self.rows && position.col < self.columns
+    }
+
+    pub fn position(&self, cell: (u32, u32)) -> Result<Position, ConfigError> {
+        let position = Position::from(cell);
+        match self.contains(position) {
+            true => Ok(position),
+            false => Err(ConfigError::CellOutOfBounds { cell }),
+        }
+    }
+
+    // the cell reached by the movement, `None` when it leaves the grid or when it isn't a movement
+    pub fn step(&self, from: Position, movement: &Move) -> Option<Position> {
+        let (row, col) = match movement {
+            Move::Up => (from.row.checked_sub(1)?, from.col),
+            Move::Down => (from.row.checked_add(1)?, from.col),
+            Move::Left => (from.row, from.col.checked_sub(1)?),
+            Move::Right => (from.row, from.col.checked_add(1)?),
+            _ => return None,
+        };
+        let to = Position::new(row, col);
+        self.contains(to).then_some(to)
+    }
+
+    pub fn neighbours(&self, position: Position) -> impl Iterator<Item = Position> + '_ {
+        [Move::Up, Move::Down, Move::Right, Move::Left]
+            .into_iter()
+            .filter_map(move |movement| self.step(position, &movement))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.rows)
+            .flat_map(move |row| (0..self.columns).map(move |col| Position::new(row, col)))
+    }
+
+    pub fn is_first_row(&self, position: Position) -> bool {
+        position.row == 0
+    }
+
+    pub fn is_last_row(&self, position: Position) -> bool {
+        position.row + 1 == self.rows
+    }
+
+    pub fn is_first_column(&self, position: Position) -> bool {
+        position.col == 0
+    }
+
+    pub fn is_last_column(&self, position: Position) -> bool {
+        position.col + 1 == self.columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // wider than it is high so a mixed up row and column shows
+    const GRID: Grid = Grid {
+        rows: 3,
+        columns: 5,
+    };
+
+    fn step(row: u32, col: u32, movement: Move) -> Option<(u32, u32)> {
+        GRID.step(Position::new(row, col), &movement)
+            .map(Into::into)
+    }
+
+    #[test]
+    fn steps_stop_at_the_edges() {
+        // the first row and column, the rows and columns of 0 don't wrap around
+        assert_eq!(step(0, 2, Move::Up), None);
+        assert_eq!(step(1, 0, Move::Left), None);
+        assert_eq!(step(0, 2, Move::Down), Some((1, 2)));
+        assert_eq!(step(1, 0, Move::Right), Some((1, 1)));
+        // the last row and column
+        assert_eq!(step(2, 2, Move::Down), None);
+        assert_eq!(step(1, 4, Move::Right), None);
+        assert_eq!(step(2, 2, Move::Up), Some((1, 2)));
+        assert_eq!(step(1, 4, Move::Left), Some((1, 3)));
+        // the rows are the short side, a column past the last row is still inside
+        assert_eq!(step(2, 3, Move::Right), Some((2, 4)));
+        assert_eq!(step(1, 2, Move::Down), Some((2, 2)));
+    }
+
+    #[test]
+    fn steps_stop_at_the_corners() {
+        let corners = [
+            ((0, 0), [None, Some((1, 0)), Some((0, 1)), None]),
+            ((0, 4), [None, Some((1, 4)), None, Some((0, 3))]),
+            ((2, 0), [Some((1, 0)), None, Some((2, 1)), None]),
+            ((2, 4), [Some((1, 4)), None, None, Some((2, 3))]),
+        ];
+        for ((row, col), reached) in corners {
+            let moves = [Move::Up, Move::Down, Move::Right, Move::Left];
+            for (movement, to) in moves.into_iter().zip(reached) {
+                assert_eq!(
+                    step(row, col, movement.clone()),
+                    to,
+                    "{:?} from ({row}, {col})",
+                    movement
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_movements_step() {
+        for movement in [Move::Fix, Move::None, Move::Wait, Move::Empty] {
+            assert_eq!(step(1, 1, movement), None);
+        }
+    }
+
+    #[test]
+    fn the_neighbours_are_inside_the_grid() {
+        let neighbours = |row, col| -> Vec<(u32, u32)> {
+            GRID.neighbours(Position::new(row, col))
+                .map(Into::into)
+                .collect()
+        };
+        assert_eq!(neighbours(0, 0), vec![(1, 0), (0, 1)]);
+        assert_eq!(neighbours(2, 4), vec![(1, 4), (2, 3)]);
+        assert_eq!(neighbours(0, 2), vec![(1, 2), (0, 3), (0, 1)]);
+        assert_eq!(neighbours(1, 4), vec![(0, 4), (2, 4), (1, 3)]);
+        assert_eq!(neighbours(1, 2), vec![(0, 2), (2, 2), (1, 3), (1, 1)]);
+        // a single cell has none
+        assert_eq!(Grid::new(1, 1).neighbours(Position::new(0, 0)).count(), 0);
+    }
+
+    #[test]
+    fn positions_outside_of_the_grid_are_rejected() {
+        assert_eq!(GRID.position((2, 4)).unwrap(), Position::new(2, 4));
+        for cell in [(3, 0), (0, 5), (4, 2), (2, 5), (u32::MAX, u32::MAX)] {
+            assert!(matches!(
+                GRID.position(cell),
+                Err(ConfigError::CellOutOfBounds { cell: rejected }) if rejected == cell
+            ));
+        }
+    }
+}
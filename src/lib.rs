@@ -0,0 +1,22 @@
+pub use rand::Rng;
+
+pub mod city;
+pub mod coordinator;
+pub mod grid;
+pub mod models;
+pub mod mods;
+pub mod scenario;
+pub mod simulation;
+pub mod strategy;
+pub mod trace;
+pub mod worker;
+
+// a run is built with `Simulation::new`, its rounds are stepped through the `Stepper` that `Simulation::run_with`
+// hands to its driver, or run all at once with `Simulation::run_to_completion`
+// the repairer threads are scoped to `run_with`, the run ends when it returns and `Simulation::finish` gives its result
+pub use grid::{Grid, Position};
+pub use models::SimulationError;
+pub use scenario::Scenario;
+pub use simulation::{Outcome, Simulation, SimulationConfig, SimulationResult, Stepper};
+pub use strategy::{Strategy, StrategyFactory};
+pub use trace::Trace;
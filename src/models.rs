@@ -1,65 +1,108 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
     str::FromStr,
-    sync::{
-        atomic::{AtomicBool, AtomicU8},
-        mpsc::{Receiver, Sender},
-        Arc, Barrier, Mutex,
-    },
+    sync::Arc,
     thread::JoinHandle,
 };
 
-pub type JobTypeSender = Arc<Mutex<Sender<JobType>>>;
-pub type JobTypeReceiver = Arc<Mutex<Receiver<JobType>>>;
+use rand::{rngs::StdRng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grid::{Grid, Position},
+    simulation::MAX_DAMAGE,
+    strategy::Strategy,
+};
 
+pub type TerrainMap = Vec<Vec<Terrain>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RepairerResult {
     pub id: u32,
-    pub repairs: u32,
+    pub repairs: u32,        // the houses this repairer restored completely
+    pub repair_actions: u32, // the fixes this repairer took part in, restoring or not
     pub moves: u32,
+    pub travel_cost: u32, // the rounds spent walking, a costly cell takes more than one
+    pub final_location: Position,
+    pub known_repairs: BTreeMap<u32, u32>, // the repairs of every repairer as known by this one
     pub goal: u32,
-    pub all_players_repairs: Vec<u32>,
+    pub explored: u32, // the houses known to be visited, by this repairer or by the maps it merged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repairs_after_faults: Option<u32>, // the houses restored after the first injected fault, how the team compensated
+    pub byzantine: bool,      // writes false repair counts in its notes
+    pub corrupted_notes: u32, // the notes written with a false count, by lying or by noise
+    pub rejected_notes: u32,  // the note reads turned down by the defense
+    pub premature: bool,      // ended its explore while reachable houses were still broken
 }
-impl RepairerResult {
-    pub fn to_string(&self) -> String {
-        format!(
-            "id: {}, repairs: {}, moves: {}, all_player_repairs: {:?}, goal: {}",
-            self.id, self.repairs, self.moves, self.all_players_repairs, self.goal
-        )
+impl fmt::Display for RepairerResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "id: {}, repairs: {}, repair actions: {}, moves: {}, travel cost: {}, final_location: {}, known_repairs: {:?}, goal: {}, explored: {}",
+            self.id,
+            self.repairs,
+            self.repair_actions,
+            self.moves,
+            self.travel_cost,
+            self.final_location,
+            self.known_repairs.values().collect::<Vec<&u32>>(),
+            self.goal,
+            self.explored
+        )?;
+        if let Some(repairs) = self.repairs_after_faults {
+            write!(f, ", repairs after faults: {}", repairs)?;
+        }
+        if self.byzantine {
+            write!(f, ", byzantine")?;
+        }
+        if self.corrupted_notes > 0 {
+            write!(f, ", corrupted notes: {}", self.corrupted_notes)?;
+        }
+        if self.rejected_notes > 0 {
+            write!(f, ", rejected notes: {}", self.rejected_notes)?;
+        }
+        if self.premature {
+            write!(f, ", premature")?;
+        }
+        Ok(())
     }
 }
 #[derive(Debug)]
-pub enum JobType {
-    DecisionMaking(
-        Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>,
-        Arc<Barrier>,
-    ),
-    Execute(
-        Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>, // the matrix
-        Arc<Vec<AtomicBool>>,                               // the explore end check
-        Arc<Barrier>,                                       // the beginning barrier
-        Arc<Barrier>,                                       // the ending barrier
-    ),
-    // DecisionMade,
-    // Executed,
-    // End(RepairerResult),
-}
-#[derive(Debug)]
 pub struct Repairer {
     pub id: u32,                                    // not going to be changed
     pub thread: Option<JoinHandle<()>>,             // not going to be changed
-    pub total_broken: u32,                          // not going to be changed
-    pub total_fixed: u32,                           // ▶️ will be changed in the execute
+    pub total_broken: Option<u32>, // the broken houses it can reach, not going to be changed, unknown when houses may break during the run
+    pub blocked: Arc<CellSet>,     // the impassable cells, not going to be changed
+    pub terrain: Arc<TerrainMap>,  // the movement cost of the cells, not going to be changed
+    pub busy_rounds: u32,          // ⏸️  ▶️ the rounds left to cross the current cell
+    pub travel_cost: u32,          // ▶️ will be changed in the execute
+    pub total_fixed: u32,          // ▶️ the restored houses, will be changed in the execute
+    pub repair_actions: u32,       // ▶️ every fix, will be changed in the execute
+    pub repair_power: u8,          // the damage removed by a single fix, not going to be changed
     pub other_repairers_repairs: HashMap<u32, u32>, // ⏸️  ▶️ will be changed in the execute and decision making
     pub total_moves: u32,                           // ▶️ will be changed in the execute
     // pub receiver: Arc<Mutex<Receiver<Command>>>,// the spawned thread will only need that so we do not save this value in the thread state
-    pub current_algorithm: MovementAlgorithm, // ⏸️ change in decision making
-    pub current_location: (u32, u32),         // ▶️ chang in execute
-    pub matrix_size: u32,                     // not going to be changed
-    pub decision: Move,                       // ⏸️  ▶️ change in decision making and in execute
-    pub move_turn: bool,                      // ▶️ change in execute
-    pub last_move_rotated: bool,
-    pub last_move: Move,
-    pub result: String,
+    pub strategy: Box<dyn Strategy>, // ⏸️ picks the movements, changes its own state in decision making
+    pub current_location: Position,  // ▶️ chang in execute
+    pub grid: Grid,                  // not going to be changed
+    pub decision: Move,              // ⏸️  ▶️ change in decision making and in execute
+    pub move_turn: bool,             // ▶️ change in execute
+    pub rng: StdRng, // derived from the simulation seed, drives every decision draw of this repairer
+    pub round: u32,  // ⏸️ the round of the last decision making
+    pub last_event: Option<TraceEvent>, // ▶️ what happened in the last execute, taken by the main thread
+    pub failure: Option<SimulationError>, // ▶️ why the last execute failed, taken by the main thread
+    pub map: CityMap,                     // ⏸️  ▶️ what this repairer knows of the city
+    pub share_maps: bool, // publishes the map next to every note, not going to be changed
+    pub lockstep: bool, // the fixes of a round are pooled and applied by a single claimer, not going to be changed
+    pub byzantine: bool, // writes false repair counts, not going to be changed
+    pub note_noise: f64, // the chance of a written note carrying a random count, not going to be changed
+    pub defense: NoteDefense, // how the notes of the others are checked, not going to be changed
+    pub known_rounds: HashMap<u32, u32>, // ⏸️ the round of the last accepted note of each repairer
+    pub corrupted_notes: u32, // ▶️ will be changed in the execute
+    pub rejected_notes: u32, // ⏸️ will be changed in decision making
+    pub reported: Option<RepairerResult>, // the last state sent by the process of the repairer, the rest of this state only mirrors it
 }
 
 impl Repairer {
@@ -70,9 +113,121 @@ impl Repairer {
         }
         tmp_total_fix
     }
+
+    // takes in what a note tells, the repairs of its writer and the map it carries
+    pub fn merge_note(&mut self, note: &Note, payload: Option<&CityMap>) {
+        // the number of the fixes can not be reduced, the own value is updated in the execute
+        if note.id != self.id && self.other_repairers_repairs[&note.id] < note.num_repairs {
+            if self.is_plausible(note) {
+                self.other_repairers_repairs
+                    .insert(note.id, note.num_repairs)
+                    .unwrap();
+                self.known_rounds.insert(note.id, note.round);
+            } else {
+                self.rejected_notes += 1;
+            }
+        }
+        if let Some(map) = payload {
+            self.map.merge(map);
+        }
+    }
+
+    // a repairer restores at most one house per round and the note of a fixing round already counts its fix,
+    // so a count can't grow by more than the rounds since the last accepted note, or than the elapsed rounds for the first one
+    fn is_plausible(&self, note: &Note) -> bool {
+        match self.defense {
+            NoteDefense::Trust => true,
+            NoteDefense::Bounded => {
+                let known = self.other_repairers_repairs[&note.id];
+                let possible = match self.known_rounds.get(&note.id) {
+                    Some(known_round) => note.round.checked_sub(*known_round),
+                    None => Some(note.round + 1),
+                };
+                possible.is_some_and(|possible| note.num_repairs - known <= possible)
+            }
+        }
+    }
+
+    // the count written in the next note
+    // a liar claims the houses it doesn't know to be repaired by the others, the noise writes a random count
+    pub fn reported_repairs(&mut self) -> u32 {
+        let reported = if self.byzantine {
+            let others = self.get_total_fixes_from_notes() - self.total_fixed;
+            match self.total_broken {
+                Some(total) => total.saturating_sub(others).max(self.total_fixed),
+                None => self.round.max(self.total_fixed),
+            }
+        } else if self.note_noise > 0.0 && self.rng.gen_bool(self.note_noise) {
+            self.rng
+                .gen_range(0..=self.total_broken.unwrap_or(self.round))
+        } else {
+            self.total_fixed
+        };
+        if reported != self.total_fixed {
+            self.corrupted_notes += 1;
+        }
+        reported
+    }
+
+    pub fn to_result(&self) -> RepairerResult {
+        if let Some(result) = &self.reported {
+            return result.clone();
+        }
+        RepairerResult {
+            id: self.id,
+            repairs: self.total_fixed,
+            repair_actions: self.repair_actions,
+            moves: self.total_moves,
+            travel_cost: self.travel_cost,
+            final_location: self.current_location,
+            known_repairs: self
+                .other_repairers_repairs
+                .iter()
+                .map(|(id, repairs)| (*id, *repairs))
+                .collect(),
+            goal: self.total_broken.unwrap_or(0),
+            explored: self.map.visited.len(),
+            repairs_after_faults: None,
+            byzantine: self.byzantine,
+            corrupted_notes: self.corrupted_notes,
+            rejected_notes: self.rejected_notes,
+            premature: false,
+        }
+    }
+
+    // forgets the repairs of the others, they are learnt again from the notes
+    pub fn forget(&mut self) {
+        let id = self.id;
+        for (other, repairs) in self.other_repairers_repairs.iter_mut() {
+            if *other != id {
+                *repairs = 0;
+            }
+        }
+        // the bounded defense takes the next note of each of them as a first one
+        self.known_rounds.clear();
+    }
 }
 
-#[derive(PartialEq, Clone, Debug)]
+// a single decision and its execution
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    #[serde(rename = "i")]
+    pub id: u32,
+    #[serde(rename = "m")]
+    pub decision: Move,
+    #[serde(rename = "f")]
+    pub from: Position,
+    #[serde(rename = "t")]
+    pub to: Position,
+    #[serde(rename = "x", default, skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<bool>, // whether the fix restored the house, only set for the `Fix` moves
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub damage: Option<u8>, // the damage left on the house, only set for the repairer applying the fixes of the round
+    #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<u32>, // the repairs written in the note left on <to>
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum Move {
     Up,
     Down,
@@ -80,15 +235,13 @@ pub enum Move {
     Left,
     Fix,
     None,  // this means the end of the explore and there is no more move available
+    Wait,  // still crossing a costly cell
     Empty, // the actual None value, no moves for now
 }
 
 impl Move {
     pub fn is_horizontal(&self) -> bool {
-        match self {
-            Self::Left | Self::Right => true,
-            _ => false,
-        }
+        matches!(self, Self::Left | Self::Right)
     }
 
     pub fn rotate_dir(&mut self) {
@@ -101,17 +254,253 @@ impl Move {
             }
         }
     }
-    pub fn apply_on_index(&self, index: (u32, u32)) -> (u32, u32) {
+}
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    GridTooSmall { rows: u32, columns: u32 },
+    GridTooLarge { rows: u32, columns: u32, max: u32 },
+    RepairersOutOfRange { repairers: u32, max: u32 },
+    InvalidBrokenRange { min: u32, max: u32 },
+    TooManyBroken { broken: u32, max: u32 },
+    NoAlgorithms,
+    CellOutOfBounds { cell: (u32, u32) },
+    DuplicateBroken { cell: (u32, u32) },
+    InvalidDamage { cell: (u32, u32), damage: u8 },
+    DamageMismatch { broken: u32, damage: u32 },
+    InvalidDamageRange { min: u8, max: u8 },
+    InvalidRepairPower { power: u8 },
+    InvalidAftershockRate { rate: f64 },
+    BlockedCell { cell: (u32, u32) },
+    NoTermination,
+    UnknownRepairer { repairer: u32, repairers: u32 },
+    InvalidNoteNoise { noise: f64 },
+    TooManyRounds { rounds: u32, max: u32 },
+    NeedsLockstep { feature: &'static str },
+    NeedsThreads { feature: &'static str },
+    MissingWorkerProgram,
+    TooManyLiars { liars: u32, repairers: u32 },
+    StateFile { path: PathBuf, message: String },
+    OutOfMemory { message: String }, // the state array of the city couldn't be allocated
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GridTooSmall { rows, columns } => {
+                write!(f, "the grid must be at least 2x2, got {}x{}", rows, columns)
+            }
+            Self::GridTooLarge { rows, columns, max } => write!(
+                f,
+                "the grid can't have more than {} cells, got {}x{}",
+                max, rows, columns
+            ),
+            Self::RepairersOutOfRange { repairers, max } => write!(
+                f,
+                "the amount of repairers must be between 1 and {}, got {}",
+                max, repairers
+            ),
+            Self::InvalidBrokenRange { min, max } => write!(
+                f,
+                "the minimum amount of broken houses ({}) is bigger than the maximum ({})",
+                min, max
+            ),
+            Self::TooManyBroken { broken, max } => write!(
+                f,
+                "{} broken houses can not be placed, this grid fits at most {}",
+                broken, max
+            ),
+            Self::NoAlgorithms => write!(f, "at least one movement algorithm is required"),
+            Self::CellOutOfBounds { cell } => {
+                write!(f, "the cell {:?} is outside of the grid", cell)
+            }
+            Self::DuplicateBroken { cell } => {
+                write!(f, "the broken house {:?} is listed more than once", cell)
+            }
+            Self::InvalidDamage { cell, damage } => write!(
+                f,
+                "the damage of the house {:?} must be between 1 and {}, got {}",
+                cell, MAX_DAMAGE, damage
+            ),
+            Self::DamageMismatch { broken, damage } => write!(
+                f,
+                "{} damage levels were given for {} broken houses",
+                damage, broken
+            ),
+            Self::InvalidDamageRange { min, max } => write!(
+                f,
+                "the damage range must be within 1 and {}, got {} to {}",
+                MAX_DAMAGE, min, max
+            ),
+            Self::InvalidRepairPower { power } => write!(
+                f,
+                "the repair power must be between 1 and {}, got {}",
+                MAX_DAMAGE, power
+            ),
+            Self::InvalidAftershockRate { rate } => {
+                write!(
+                    f,
+                    "the aftershock rate must be between 0 and 1, got {}",
+                    rate
+                )
+            }
+            Self::BlockedCell { cell } => write!(
+                f,
+                "the cell {:?} is blocked, it can't hold a broken house, a repairer, an aftershock or a terrain",
+                cell
+            ),
+            Self::NoTermination => write!(
+                f,
+                "runs with aftershocks need a round limit, a time limit or a quiescence period"
+            ),
+            Self::UnknownRepairer {
+                repairer,
+                repairers,
+            } => write!(
+                f,
+                "the fault targets the repairer {} but there are only {} repairers",
+                repairer, repairers
+            ),
+            Self::NeedsLockstep { feature } => write!(
+                f,
+                "{} need the lockstep mode, the free running repairers have no shared rounds",
+                feature
+            ),
+            Self::NeedsThreads { feature } => write!(
+                f,
+                "{} need the repairers to be threads of a single process",
+                feature
+            ),
+            Self::MissingWorkerProgram => write!(
+                f,
+                "the processes mode needs the program to start the repairer processes with"
+            ),
+            Self::InvalidNoteNoise { noise } => write!(
+                f,
+                "the note noise must be between 0 and 1, got {}",
+                noise
+            ),
+            Self::TooManyRounds { rounds, max } => write!(
+                f,
+                "the notes can't carry more than {} rounds, got a limit of {}",
+                max, rounds
+            ),
+            Self::TooManyLiars { liars, repairers } => write!(
+                f,
+                "{} byzantine repairers were asked for but there are only {} repairers",
+                liars, repairers
+            ),
+            Self::StateFile { path, message } => write!(
+                f,
+                "couldn't map the state file {}: {}",
+                path.display(),
+                message
+            ),
+            Self::OutOfMemory { message } => write!(f, "the city doesn't fit in memory: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// a failure of a running simulation, the run is stopped instead of panicking in the repairer threads
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimulationError {
+    InvalidMove {
+        repairer: u32,
+        round: u32,
+        from: Position,
+        decision: Move,
+    },
+    MissingDecision {
+        repairer: u32,
+        round: u32,
+    },
+    Panicked {
+        repairer: u32,
+        round: u32,
+        message: String,
+    },
+    Crashed {
+        repairer: u32,
+        round: u32,
+        message: String, // how the process of the repairer ended
+    },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Right => (index.0, index.1 + 1),
-            Self::Left => (index.0, index.1 - 1),
-            Self::Up => (index.0 - 1, index.1),
-            Self::Down => (index.0 + 1, index.1),
-            _ => panic!("incorrect move to be applied !!"), // impossible
+            Self::InvalidMove {
+                repairer,
+                round,
+                from,
+                decision,
+            } => write!(
+                f,
+                "repairer {} tried to move {:?} out of the grid from {} in round {}",
+                repairer, decision, from, round
+            ),
+            Self::MissingDecision { repairer, round } => write!(
+                f,
+                "repairer {} had no decision to execute in round {}",
+                repairer, round
+            ),
+            Self::Panicked {
+                repairer,
+                round,
+                message,
+            } => write!(
+                f,
+                "repairer {} panicked in round {}: {}",
+                repairer, round, message
+            ),
+            Self::Crashed {
+                repairer,
+                round,
+                message,
+            } => write!(
+                f,
+                "repairer {} died in round {}: {}",
+                repairer, round, message
+            ),
         }
     }
 }
-#[derive(Clone, Debug)]
+
+impl SimulationError {
+    // the id of the failed repairer
+    pub fn repairer(&self) -> u32 {
+        match self {
+            Self::InvalidMove { repairer, .. }
+            | Self::MissingDecision { repairer, .. }
+            | Self::Panicked { repairer, .. }
+            | Self::Crashed { repairer, .. } => *repairer,
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+// the kind of ground of a cell, entering a cell takes as many rounds as its cost
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Terrain {
+    #[default]
+    Road,
+    Rubble,
+}
+
+impl Terrain {
+    pub fn cost(&self) -> u32 {
+        match self {
+            Self::Road => 1,
+            Self::Rubble => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum MovementAlgorithm {
     BRD, // BFS right and down
     BLD, // BFS left and down
@@ -215,28 +604,288 @@ impl MovementAlgorithm {
     }
 }
 
+impl FromStr for MovementAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "BRD" => Ok(Self::BRD),
+            "BLD" => Ok(Self::BLD),
+            "BRU" => Ok(Self::BRU),
+            "BLU" => Ok(Self::BLU),
+            "DDR" => Ok(Self::DDR),
+            "DDL" => Ok(Self::DDL),
+            "DUR" => Ok(Self::DUR),
+            "DUL" => Ok(Self::DUL),
+            _ => Err(format!(
+                "unknown movement algorithm `{}`, expected one of BRD, BLD, BRU, BLU, DDR, DDL, DUR, DUL",
+                s
+            )),
+        }
+    }
+}
+
+// how a repairer checks the repair counts it reads from the notes of the others
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteDefense {
+    #[default]
+    Trust, // takes every increase
+    Bounded, // takes the increases a repairer could have made in the rounds since its last accepted note
+}
+
+impl FromStr for NoteDefense {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trust" => Ok(Self::Trust),
+            "bounded" => Ok(Self::Bounded),
+            _ => Err(format!(
+                "unknown note defense `{}`, expected one of trust, bounded",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NoteDefense {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trust => write!(f, "trust"),
+            Self::Bounded => write!(f, "bounded"),
+        }
+    }
+}
+
+impl fmt::Display for MovementAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// a note left by a repairer on a house, one slot per repairer on every house
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Note {
-    pub id: u32,
-    pub num_repairs: u32,
+    pub id: u32,          // the writer, which is also the index of the slot
+    pub num_repairs: u32, // the houses repaired by the writer so far
+    pub round: u32,       // the round the note was written in
+    pub flags: u8,        // <Note::WRITTEN> and the optional fields carried by the note
 }
 
 impl Note {
-    pub fn parse(raw_string: &String) -> Self {
-        let parts: Vec<&str> = raw_string.split_whitespace().collect();
-        // Handle invalid string format,
-        if parts.len() != 4 || parts[1] != "repaired" || parts[3] != "times" {
-            panic!("unexpected format of string detected as a note !!") // Almost impossible panic
+    pub const WRITTEN: u8 = 0b0000_0001;
+    pub const PAYLOAD: u8 = 0b0000_0010; // a map was published next to the note
+    const HEADING_SHIFT: u8 = 4; // bits 4 to 6 hold the heading of the writer, 0 when unknown
+
+    // the packed layout is | flags: 8 bits | round: 24 bits | repairs: 32 bits |
+    const ROUND_MASK: u64 = 0x00ff_ffff;
+    pub const MAX_ROUND: u32 = Self::ROUND_MASK as u32; // the runs end before the rounds outgrow the notes
+
+    pub fn new(id: u32, num_repairs: u32, round: u32) -> Self {
+        Self {
+            id,
+            num_repairs,
+            round,
+            flags: Self::WRITTEN,
         }
-        let id = u32::from_str(parts[0])
-            .ok()
-            .expect("couldn't parse the repairer id from note !");
-        let num_repairs =
-            u32::from_str(parts[2]).expect("couldn't parse the repair times from note !");
+    }
 
-        Self { id, num_repairs }
+    pub fn with_heading(mut self, heading: &Move) -> Self {
+        let code = match heading {
+            Move::Up => 1,
+            Move::Down => 2,
+            Move::Right => 3,
+            Move::Left => 4,
+            _ => 0,
+        };
+        self.flags = (self.flags & !(0b111 << Self::HEADING_SHIFT)) | (code << Self::HEADING_SHIFT);
+        self
+    }
+
+    pub fn is_written(&self) -> bool {
+        self.flags & Self::WRITTEN != 0
+    }
+
+    pub fn has_payload(&self) -> bool {
+        self.flags & Self::PAYLOAD != 0
+    }
+
+    // the direction of the last movement of the writer
+    pub fn heading(&self) -> Option<Move> {
+        match (self.flags >> Self::HEADING_SHIFT) & 0b111 {
+            1 => Some(Move::Up),
+            2 => Some(Move::Down),
+            3 => Some(Move::Right),
+            4 => Some(Move::Left),
+            _ => None,
+        }
+    }
+
+    pub fn pack(&self) -> u64 {
+        ((self.flags as u64) << 56)
+            | ((self.round as u64 & Self::ROUND_MASK) << 32)
+            | self.num_repairs as u64
+    }
+
+    pub fn unpack(id: u32, raw: u64) -> Self {
+        Self {
+            id,
+            num_repairs: raw as u32,
+            round: ((raw >> 32) & Self::ROUND_MASK) as u32,
+            flags: (raw >> 56) as u8,
+        }
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} repaired {} times (round {})",
+            self.id, self.num_repairs, self.round
+        )
+    }
+}
+
+// a set of cells of the city, one bit per cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellSet {
+    columns: u32,
+    bits: Vec<u64>,
+}
+
+impl CellSet {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self {
+            columns,
+            bits: vec![0; (rows as usize * columns as usize).div_ceil(64)],
+        }
+    }
+
+    fn bit(&self, cell: (u32, u32)) -> (usize, u64) {
+        let index = cell.0 as usize * self.columns as usize + cell.1 as usize;
+        (index / 64, 1 << (index % 64))
+    }
+
+    pub fn insert(&mut self, cell: (u32, u32)) -> bool {
+        let (word, mask) = self.bit(cell);
+        let inserted = self.bits[word] & mask == 0;
+        self.bits[word] |= mask;
+        inserted
+    }
+
+    pub fn remove(&mut self, cell: (u32, u32)) -> bool {
+        let (word, mask) = self.bit(cell);
+        let removed = self.bits[word] & mask != 0;
+        self.bits[word] &= !mask;
+        removed
+    }
+
+    pub fn contains(&self, cell: (u32, u32)) -> bool {
+        let (word, mask) = self.bit(cell);
+        self.bits[word] & mask != 0
+    }
+
+    pub fn union_with(&mut self, other: &CellSet) {
+        for (word, other) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *word |= other;
+        }
+    }
+
+    pub fn difference_with(&mut self, other: &CellSet) {
+        for (word, other) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *word &= !other;
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.bits.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let columns = self.columns;
+        self.bits.iter().enumerate().flat_map(move |(word, bits)| {
+            (0..64)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| {
+                    let index = word as u32 * 64 + bit;
+                    (index / columns, index % columns)
+                })
+        })
+    }
+}
+
+// what a repairer knows of the city, either seen by itself or merged from the notes of the others
+// the repaired cells win the merge, a house broken again by an aftershock is only learned by seeing it
+#[derive(Clone, Debug, PartialEq)]
+pub struct CityMap {
+    pub broken: CellSet,   // houses known to be broken
+    pub repaired: CellSet, // houses known to be repaired
+    pub visited: CellSet,  // houses someone has stood on
+}
+
+impl CityMap {
+    pub fn new(rows: u32, columns: u32) -> Self {
+        Self {
+            broken: CellSet::new(rows, columns),
+            repaired: CellSet::new(rows, columns),
+            visited: CellSet::new(rows, columns),
+        }
+    }
+
+    // records what was seen on a house
+    pub fn observe(&mut self, cell: (u32, u32), broken: bool) {
+        self.visited.insert(cell);
+        if broken {
+            self.broken.insert(cell);
+            self.repaired.remove(cell);
+        } else if self.broken.remove(cell) {
+            self.repaired.insert(cell);
+        }
+    }
+
+    pub fn merge(&mut self, other: &CityMap) {
+        self.visited.union_with(&other.visited);
+        self.repaired.union_with(&other.repaired);
+        self.broken.union_with(&other.broken);
+        self.broken.difference_with(&self.repaired);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_survive_packing() {
+        for heading in [Move::Up, Move::Down, Move::Right, Move::Left, Move::Fix] {
+            let note = Note::new(3, u32::MAX, Note::MAX_ROUND).with_heading(&heading);
+            let unpacked = Note::unpack(3, note.pack());
+            assert_eq!(unpacked, note);
+            assert!(unpacked.is_written());
+            assert!(!unpacked.has_payload());
+            assert_eq!(
+                unpacked.heading(),
+                (heading != Move::Fix).then_some(heading)
+            );
+        }
+        let flagged = Note {
+            flags: Note::WRITTEN | Note::PAYLOAD,
+            ..Note::new(0, 7, 0)
+        };
+        assert!(Note::unpack(0, flagged.pack()).has_payload());
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{} repaired {} times", self.id, self.num_repairs)
+    #[test]
+    fn an_empty_slot_holds_no_note() {
+        let note = Note::unpack(5, 0);
+        assert!(!note.is_written());
+        assert_eq!(note.heading(), None);
+        assert_eq!((note.id, note.num_repairs, note.round), (5, 0, 0));
     }
 }
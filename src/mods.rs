@@ -1,25 +1,105 @@
-use std::{ops::Add, process::Command, sync::{
-    atomic::{AtomicBool, AtomicU8, Ordering}, Arc, Mutex
-}};
+use std::{
+    ops::Add,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use rand::{seq::index, Rng};
+
+use crate::{
+    city::Matrix,
+    grid::{Grid, Position},
+    models::{CellSet, Move, Note, Repairer, SimulationError, TraceEvent},
+    strategy::{CellView, RepairerView},
+};
+
+// picks <amount> distinct random cells without listing all of them, cells are reused only when there is more requested than the matrix has
+pub fn gen_rand_cells<R: Rng>(
+    rng: &mut R,
+    amount: u32,
+    rows: u32,
+    columns: u32,
+) -> Vec<(u32, u32)> {
+    let total = rows as usize * columns as usize;
+    let mut cells: Vec<(u32, u32)> = Vec::with_capacity(amount as usize);
+    while cells.len() < amount as usize {
+        let remaining = (amount as usize - cells.len()).min(total);
+        for cell in index::sample(rng, total, remaining) {
+            cells.push((
+                (cell / columns as usize) as u32,
+                (cell % columns as usize) as u32,
+            ));
+        }
+    }
+    cells
+}
 
-use rand::{prelude::SliceRandom, thread_rng, Rng};
+// the claim a repairer leaves on a broken house when deciding to fix it, the highest claim of a round
+// belongs to the lowest id so the winner of a fix race doesn't depend on the threads scheduling
+pub fn fix_claim(round: u32, id: u32) -> u64 {
+    ((round as u64) << 32) | (u32::MAX - id) as u64
+}
 
-use crate::models::{ Move, Note, Repairer };
+// labels the connected walkable cells, the repairers of a region can only reach the houses of the same region
+pub fn label_regions(grid: &Grid, blocked: &CellSet) -> Vec<Vec<Option<u32>>> {
+    let mut regions: Vec<Vec<Option<u32>>> =
+        vec![vec![None; grid.columns as usize]; grid.rows as usize];
+    let mut next_region = 0;
+    for start in grid.positions() {
+        if blocked.contains(start.into())
+            || regions[start.row as usize][start.col as usize].is_some()
+        {
+            continue;
+        }
+        // flood filling the region
+        let mut stack = vec![start];
+        regions[start.row as usize][start.col as usize] = Some(next_region);
+        while let Some(position) = stack.pop() {
+            for next in grid.neighbours(position) {
+                if !blocked.contains(next.into())
+                    && regions[next.row as usize][next.col as usize].is_none()
+                {
+                    regions[next.row as usize][next.col as usize] = Some(next_region);
+                    stack.push(next);
+                }
+            }
+        }
+        next_region += 1;
+    }
+    regions
+}
 
-pub fn gen_rand_index(amount: i32, min: i32, max: i32) -> Vec<i32> {
-    let mut rng = rand::thread_rng();
-    let mut numbers: Vec<i32> = (min..max).collect();
-    numbers.shuffle(&mut rng);
-    numbers.iter().take(amount as usize).cloned().collect()
+pub fn print_matrix(matrix: &Matrix, blocked: &CellSet, repairers: &[Position]) {
+    print_grid(&matrix.damages(), blocked, repairers);
 }
 
-pub fn print_matrix(matrix: &Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>, repairers: Vec<(u32, u32)>) {
-    println!("   repairer 1    |    repairer 2    |    repairer 3    |    repairer 4    ");
-    println!("     {:?}             {:?}            {:?}            {:?}        ", repairers[0], repairers[1], repairers[2], repairers[3]);
+// prints a snapshot of the house values along with the repairers locations, the blocked cells are shown as `#`
+pub fn print_grid(cells: &[Vec<u8>], blocked: &CellSet, repairers: &[Position]) {
+    // four repairers per line
+    for (line, chunk) in repairers.chunks(4).enumerate() {
+        let mut titles: Vec<String> = Vec::new();
+        let mut locations: Vec<String> = Vec::new();
+        for (idx, location) in chunk.iter().enumerate() {
+            titles.push(format!(
+                "{:^16}",
+                format!("repairer {}", line * 4 + idx + 1)
+            ));
+            locations.push(format!("{:^16}", location.to_string()));
+        }
+        println!("{}", titles.join(" | "));
+        println!("{}", locations.join("   "));
+    }
     println!();
-    for row in matrix.iter() {
-        for element in row.iter() {
-            print!("{:?} | ", element.1);
+    for (row_idx, row) in cells.iter().enumerate() {
+        for (col_idx, element) in row.iter().enumerate() {
+            if blocked.contains((row_idx as u32, col_idx as u32)) {
+                print!("# | ");
+            } else {
+                print!("{:?} | ", element);
+            }
         }
         println!();
         for _ in row.iter() {
@@ -29,250 +109,264 @@ pub fn print_matrix(matrix: &Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>,
     }
 }
 
-pub fn make_decision(
-    repairer: Arc<Mutex<Repairer>>,
-    // barrier: Arc<Barrier>,
-    matrix: Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>,
-) -> bool {
+pub fn make_decision(repairer: &Mutex<Repairer>, matrix: &Matrix, round: u32) -> bool {
     let mut repairer = match repairer.lock() {
-        Ok(r) => {
-            r}, 
+        Ok(r) => r,
         Err(e) => {
-            println!("{}", e.to_string());
-            panic!("{}", e.to_string())
+            println!("{}", e);
+            panic!("{}", e)
         }
     };
-    // based on the turn which will either be a breath or depth move we will find the sensitive houses that the algorithm must be rotated.
-    // the rotation is applied on the algorithm of the specific thread,
-    // if the thread is on a BFS turn and the current index is a sensitive index we rotate the BFS direction and will update the new algo on the threads state.
-    
-    // getting the next move in condition that nothing is checked
-    let mut rng = thread_rng();
-
-    let mut n_move: Move = repairer.current_algorithm.get_move(rng.gen_bool(1.0 / 3.0));
-    if repairer.last_move_rotated {
-        n_move = repairer.last_move.clone();
-        repairer.last_move_rotated = false;
+    let repairer = &mut *repairer;
+    repairer.round = round;
+
+    // still crossing a costly cell, nothing else can be done
+    if repairer.busy_rounds > 0 {
+        repairer.busy_rounds -= 1;
+        repairer.decision = Move::Wait;
+        repairer.strategy.skipped(&repairer.decision);
+        return true;
     }
-    // checking the conditions that may change the algo
+
+    let location = repairer.current_location;
+    let house = matrix.house(location);
 
     // checking the current index status -> might change to Move::Fix
-    let current_value = matrix[repairer.current_location.0 as usize]
-        [repairer.current_location.1 as usize].1
-        .load(Ordering::Relaxed);
-    if current_value == 11 {
-        n_move = Move::Fix;
-
-        repairer.decision = n_move.clone();   
-                                            
-        if repairer.last_move_rotated {
-          repairer.last_move_rotated = false;
+    let current_value = house.damage().load(Ordering::Relaxed);
+    repairer.map.observe(location.into(), current_value > 0);
+    if current_value > 0 {
+        // claiming the house for this round and pledging the repair power, the winner applies all of the pledges
+        if repairer.lockstep {
+            house
+                .claim()
+                .fetch_max(fix_claim(round, repairer.id), Ordering::Relaxed);
+            house
+                .work()
+                .fetch_add(repairer.repair_power as u32, Ordering::Relaxed);
         }
 
+        repairer.decision = Move::Fix;
+        repairer.strategy.skipped(&repairer.decision);
         return true; // return true because the first priority is the fixing
     }
 
     // reading the notes // might change to Move::None
-    for (note_idx, note) in matrix[repairer.current_location.0 as usize]
-    [repairer.current_location.1 as usize].0.iter().enumerate() {
-        let num_repairs = Note::parse(match &note.lock() {Ok(n) => n, Err(e) => panic!("{}",e.to_string())}).num_repairs;
-        // checking with the previous value of the repairers value
-        if **(repairer
-            .other_repairers_repairs
-            .get(&(note_idx as u32))
-            .as_ref()
-            .unwrap())
-            < num_repairs // the number of the fixes can not be reduced so != will do the job and there is no need for greater and smaller than sign.
-            && 
-            // the current threads state was updated in the last round of the execute function
-            note_idx as u32 != repairer.id
-        {   
-            // updating the specific repairer total repairs
-            repairer
-                .other_repairers_repairs
-                .insert(note_idx as u32, num_repairs).unwrap();
-
-        }
-    }
-        if repairer.get_total_fixes_from_notes() == repairer.total_broken  {
-            n_move = Move::None;
-            repairer.decision = n_move.clone();
-            return true;
-        }
-
-    // checking the index // might rotate tha algo
-    // case 1 => corners
-    let corners = [
-        (0, 0),
-        (0, repairer.matrix_size - 1),
-        (repairer.matrix_size - 1, repairer.matrix_size - 1),
-        (repairer.matrix_size - 1, 0),
-    ];
-    if  repairer.current_location == corners[0] && (n_move == Move::Left || n_move == Move::Up ){
-        // updating the threads state
-        repairer.current_algorithm.rotate_algo(&n_move);
-        repairer.last_move_rotated = true;
-        n_move.rotate_dir();
-        repairer.last_move = n_move.clone();
-        repairer.decision = n_move.clone();
-    } else if  repairer.current_location == corners[1] && (n_move == Move::Right || n_move == Move::Up ){
-        // updating the threads state
-        repairer.current_algorithm.rotate_algo(&n_move);
-        repairer.last_move_rotated = true;
-        n_move.rotate_dir();
-        repairer.last_move = n_move.clone();
-        repairer.decision = n_move.clone();
-    } else if  repairer.current_location == corners[2] && (n_move == Move::Right || n_move == Move::Down ){
-        // updating the threads state
-        repairer.current_algorithm.rotate_algo(&n_move);
-        repairer.last_move_rotated = true;
-        n_move.rotate_dir();
-        repairer.last_move = n_move.clone();
-        repairer.decision = n_move.clone();
-    } else if repairer.current_location == corners[3] && (n_move == Move::Left || n_move == Move::Down ){
-        // updating the threads state
-        repairer.current_algorithm.rotate_algo(&n_move);
-        repairer.last_move_rotated = true;
-        n_move.rotate_dir();
-        repairer.last_move = n_move.clone();
-        repairer.decision = n_move.clone();
-    } else { 
-        if !repairer.last_move_rotated {
-        // case 2 => edges
-        if n_move.is_horizontal() {
-            // checking the right and the left edges
-            // checking if the col value is 0 or <matrix_size - 1>
-            if repairer.current_location.1 == 0 {
-                // on the left edge, changing if next move is left
-                if n_move == Move::Left {
-                    repairer.current_algorithm.rotate_algo(&n_move);
-                    n_move.rotate_dir();
-                    repairer.decision = n_move.clone();
-                }
-            } else if repairer.current_location.1 == repairer.matrix_size - 1 {
-                // on the right edge, changing if next move is right
-                if n_move == Move::Right {
-                    repairer.current_algorithm.rotate_algo(&n_move);
-                    n_move.rotate_dir();
-                    repairer.decision = n_move.clone();
-                }
-            }
-        } else {
-            // checking the bottom and the top edges
-            // checking if the row value is 0 or <matrix_size - 1>
-            if repairer.current_location.0 == 0 {
-                // on the upper edge, changing if next move is up
-                if n_move == Move::Up {
-                    repairer.current_algorithm.rotate_algo(&n_move);
-                    n_move.rotate_dir();
-                    repairer.decision = n_move.clone();
-                }
-            } else if repairer.current_location.0 == repairer.matrix_size - 1 {
-                // on the bottom edge, changing if next move is down
-                if n_move == Move::Down {
-                    repairer.current_algorithm.rotate_algo(&n_move);
-                    n_move.rotate_dir();
-                    repairer.decision = n_move.clone();
-                }
-            }
-        }
+    let notes: Vec<Note> = house.notes().collect();
+    for note in notes.iter() {
+        let payload = match note.has_payload() && note.id != repairer.id {
+            true => house.note(note.id).load_payload(),
+            false => None,
+        };
+        repairer.merge_note(note, payload.as_deref());
     }
+    // false notes can push the known repairs past the total
+    let known = repairer.get_total_fixes_from_notes();
+    if repairer.total_broken.is_some_and(|total| known >= total) {
+        repairer.decision = Move::None;
+        repairer.strategy.skipped(&repairer.decision);
+        return true;
     }
-    // sending the confirmation
 
-    if repairer.decision == Move::Empty {
-        repairer.decision = n_move.clone();
+    // asking the strategy for the movement
+    let view = RepairerView {
+        id: repairer.id,
+        round,
+        location,
+        grid: repairer.grid,
+        total_broken: repairer.total_broken,
+        total_fixed: repairer.total_fixed,
+        total_moves: repairer.total_moves,
+        known_repairs: &repairer.other_repairers_repairs,
+        map: &repairer.map,
+        blocked: &repairer.blocked,
+        terrain: &repairer.terrain,
+    };
+    let cell = CellView {
+        value: current_value,
+        notes: &notes,
+    };
+    let mut decision = repairer.strategy.next_move(&view, &cell, &mut repairer.rng);
+    if !view.can_move(&decision) {
+        // the strategy walked into an edge or a blocked cell, taking the first open way instead
+        decision = [Move::Up, Move::Down, Move::Right, Move::Left]
+            .into_iter()
+            .find(|side| view.can_move(side))
+            .unwrap_or(Move::None);
     }
+    repairer.decision = decision;
 
-    true // incase none of the if clauses returned the true we do the least move detected above by the <Move::get_move()> fn.
+    true
 }
 
 pub fn execute(
-    repairer: Arc<Mutex<Repairer>>,
-    // barrier: Arc<Barrier>,
-    checks : Arc<Vec<AtomicBool>>,
-    matrix: Arc<Vec<Vec<(Vec<Arc<Mutex<String>>>, AtomicU8)>>>,
-) -> bool {
+    repairer: &Mutex<Repairer>,
+    checks: &[AtomicBool],
+    matrix: &Matrix,
+) -> Result<bool, SimulationError> {
     let mut repairer = repairer.lock().unwrap();
+    let mut event = TraceEvent {
+        id: repairer.id,
+        decision: repairer.decision.clone(),
+        from: repairer.current_location,
+        to: repairer.current_location,
+        fixed: None,
+        damage: None,
+        note: None,
+    };
 
     // applying the move
     match repairer.decision {
-        Move::Empty => panic!("decision making round didn't make any decisions"),
+        Move::Empty => Err(SimulationError::MissingDecision {
+            repairer: repairer.id,
+            round: repairer.round,
+        }),
         Move::None => {
-             repairer.total_moves += 1;
+            repairer.total_moves += 1;
             checks[repairer.id as usize].store(true, Ordering::Relaxed);
-            repairer.result = format!("repairer id: {}, repairs: {}, moves: {}, all_players_repairs: {:?}, goal: {}", repairer.id, repairer.total_fixed, repairer.total_moves, repairer.other_repairers_repairs, repairer.total_broken);
-            false
+            repairer.last_event = Some(event);
+            Ok(false)
+        }
+        Move::Wait => {
+            repairer.decision = Move::Empty;
+            repairer.last_event = Some(event);
+            Ok(true)
         }
         Move::Fix => {
             // move is fix
-            // fixing
-            let exchange_res = matrix[repairer.current_location.0 as usize][repairer.current_location.1 as usize].1
-                .compare_exchange(11, 0, Ordering::Acquire, Ordering::Relaxed);
+            let house = matrix.house(repairer.current_location);
+            repairer.repair_actions += 1;
+            let work = match repairer.lockstep {
+                // fixing, only the winner of the claims applies the repair work of the round
+                true if house.claim().load(Ordering::Relaxed)
+                    == fix_claim(repairer.round, repairer.id) =>
+                {
+                    Some(house.work().swap(0, Ordering::Relaxed))
+                }
+                true => None, // another repairer with a lower id has claimed the house in this round and applies this repair too
+                // without rounds every fixer applies its own work, the compare and swap loop settles the races
+                false => Some(repairer.repair_power as u32),
+            };
+            let restored = match work {
+                Some(work) => {
+                    let (previous, left) = house.repair(work);
+                    event.damage = Some(left);
+                    previous > 0 && left == 0
+                }
+                None => false,
+            };
 
             // updating the decision
             repairer.decision = Move::Empty.clone();
 
-            match exchange_res { Ok(_) => {
-                 // updating the total fixed
-                repairer.total_fixed = repairer.total_fixed.add(1);             
-                // updating the other repairers 
-                let tmp_tf = repairer.total_fixed;
-                let tmp_id = repairer.id;               
-                repairer.other_repairers_repairs.insert(tmp_id, tmp_tf).unwrap();
-
-            }, Err(_) => {
-                // nothing
-            }}
-           
+            event.fixed = Some(restored);
+            match restored {
+                true => {
+                    // updating the total fixed
+                    repairer.total_fixed = repairer.total_fixed.add(1);
+                    // updating the other repairers
+                    let tmp_tf = repairer.total_fixed;
+                    let tmp_id = repairer.id;
+                    repairer
+                        .other_repairers_repairs
+                        .insert(tmp_id, tmp_tf)
+                        .unwrap();
+                    let location = repairer.current_location;
+                    repairer.map.observe(location.into(), false);
+                }
+                false => {
+                    // nothing
+                }
+            }
+
             // adding the total moves
             repairer.total_moves = repairer.total_moves.add(1);
 
             // leaving the note
-
-             let mut note = matrix[repairer.current_location.0 as usize]
-            [repairer.current_location.1 as usize].0[repairer.id as usize].lock().unwrap();
-            *note = format!("{} repaired {} times", repairer.id, repairer.total_fixed);
-            
-
-
+            event.note = Some(leave_note(&mut repairer, matrix, &Move::Fix));
 
             // updating the move turn
             repairer.move_turn = !repairer.move_turn;
 
-
-            true
+            repairer.last_event = Some(event);
+            Ok(true)
         }
         _ => {
             // move is actual move, changing the thread state
 
             // updating the current location
-            repairer.current_location = repairer
-                .decision
-                .apply_on_index(repairer.current_location);
+            let Some(to) = repairer
+                .grid
+                .step(repairer.current_location, &repairer.decision)
+            else {
+                return Err(SimulationError::InvalidMove {
+                    repairer: repairer.id,
+                    round: repairer.round,
+                    from: repairer.current_location,
+                    decision: repairer.decision.clone(),
+                });
+            };
+            repairer.current_location = to;
+            matrix.set_location(repairer.id, to);
+            let heading = repairer.decision.clone();
+
+            // entering the cell takes its cost in rounds, the extra ones are waited in the next decisions
+            let cost = repairer.terrain[to.row as usize][to.col as usize].cost();
+            repairer.travel_cost += cost;
+            repairer.busy_rounds = cost - 1;
 
             // updating the decision
             repairer.decision = Move::Empty.clone();
 
-
             //updating the move turn
             repairer.move_turn = !repairer.move_turn;
 
             // adding the total moves
             repairer.total_moves = repairer.total_moves.add(1);
 
+            // leaving the note
+            event.note = Some(leave_note(&mut repairer, matrix, &heading));
+            event.to = repairer.current_location;
 
-            let mut note = matrix[repairer.current_location.0 as usize]
-            [repairer.current_location.1 as usize].0[repairer.id as usize].lock().unwrap();
-            *note = format!("{} repaired {} times", repairer.id, repairer.total_fixed);
-
-            true
+            repairer.last_event = Some(event);
+            Ok(true)
         }
     }
 }
 
+// writes the note of the repairer on its current house, along with its map when the maps are shared
+// returns the repairs written in the note, a liar or the noise may write a false count
+fn leave_note(repairer: &mut Repairer, matrix: &Matrix, heading: &Move) -> u32 {
+    let slot = matrix.house(repairer.current_location).note(repairer.id);
+    let repairs = repairer.reported_repairs();
+    let note = Note::new(repairer.id, repairs, repairer.round).with_heading(heading);
+    if repairer.share_maps {
+        slot.publish(&note, Arc::new(repairer.map.clone()));
+    } else {
+        slot.store(&note);
+    }
+    repairs
+}
+
 pub fn clear_terminal() {
     let _ = Command::new("clear")
-    .status()
-    .expect("failed to execute ls");
+        .status()
+        .expect("failed to execute ls");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+
+    use super::*;
+
+    #[test]
+    fn the_lowest_id_wins_the_claims_of_a_round() {
+        let claim = AtomicU64::new(fix_claim(6, 0));
+        for id in [3, 1, 2] {
+            claim.fetch_max(fix_claim(7, id), Ordering::Relaxed);
+        }
+        assert_eq!(claim.load(Ordering::Relaxed), fix_claim(7, 1));
+        // a claim left from an earlier round never wins
+        assert!(fix_claim(7, u32::MAX - 1) > fix_claim(6, 0));
+    }
 }
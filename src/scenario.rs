@@ -0,0 +1,319 @@
+use std::{collections::HashSet, fmt, fs, io, path::Path};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    city::Matrix,
+    models::{CellSet, ConfigError, MovementAlgorithm, NoteDefense, Terrain, TerrainMap},
+    mods::gen_rand_cells,
+    simulation::{check_grid_size, SimulationConfig, MAX_DAMAGE, MAX_REPAIRERS},
+};
+
+// a fixed city layout, either loaded from a file or generated from the seed of a run
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub rows: u32,
+    pub columns: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>, // the seed of the decision draws, the run seed is used when missing
+    pub broken: Vec<(u32, u32)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub damage: Vec<u8>, // the damage of each broken house in the order of <broken>, all of them are fully damaged when empty
+    pub repairers: Vec<RepairerSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aftershocks: Vec<Aftershock>, // the houses breaking during the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<(u32, u32)>, // the impassable cells, rubble or rivers, treated like the edges of the grid
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terrain: Vec<TerrainPatch>, // the cells that aren't roads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<Fault>, // the breakdowns of the repairers during the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aftershock_rate: Option<f64>, // the chance of a random aftershock in each round, drawn from the seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note_noise: Option<f64>, // the chance of a written note carrying a random count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note_defense: Option<NoteDefense>, // how the repairers check the counts they read
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerrainPatch {
+    pub cell: (u32, u32),
+    pub terrain: Terrain,
+}
+
+// damages a house at the beginning of a round, a fine house becomes broken
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Aftershock {
+    pub round: u32,
+    pub cell: (u32, u32),
+    pub damage: u8,
+}
+
+impl Aftershock {
+    // adds the damage to the house, returns whether the house was fine before
+    pub fn strike(&self, matrix: &Matrix) -> bool {
+        matrix.break_house(self.cell.into(), self.damage)
+    }
+}
+
+// breaks a repairer down at the beginning of a round, the faults of a repairer that is already done are ignored
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fault {
+    pub round: u32,
+    pub repairer: u32,
+    pub kind: FaultKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    Stop,                  // leaves the run for good, its notes stay on the houses
+    Pause { rounds: u32 }, // waits in place for the rounds
+    Amnesia,               // forgets the repairs of the others
+    Crash,                 // dies mid-run, its process is killed in the processes mode
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            FaultKind::Stop => write!(
+                f,
+                "repairer {} stopped in round {}",
+                self.repairer, self.round
+            ),
+            FaultKind::Pause { rounds } => write!(
+                f,
+                "repairer {} paused for {} rounds in round {}",
+                self.repairer, rounds, self.round
+            ),
+            FaultKind::Amnesia => write!(
+                f,
+                "repairer {} lost its memory in round {}",
+                self.repairer, self.round
+            ),
+            FaultKind::Crash => write!(
+                f,
+                "repairer {} crashed in round {}",
+                self.repairer, self.round
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepairerSpec {
+    pub location: (u32, u32),
+    pub algorithm: MovementAlgorithm,
+    #[serde(default = "full_power")]
+    pub power: u8, // the damage removed by a single fix
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub byzantine: bool, // writes false repair counts in its notes
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn full_power() -> u8 {
+    MAX_DAMAGE
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "couldn't access the scenario file: {}", e),
+            Self::Format(e) => write!(f, "invalid scenario file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<io::Error> for ScenarioError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ScenarioError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Format(e)
+    }
+}
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScenarioError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // draws a random layout from the parameters of the config, the config must be validated already
+    pub fn generate<R: Rng>(config: &SimulationConfig, rng: &mut R) -> Self {
+        // how many broken houses ? between 3 to 6 by default for the sake of simplicity
+        let total_broken = config
+            .total_broken
+            .unwrap_or_else(|| rng.gen_range(config.broken_range.0..=config.broken_range.1));
+
+        // distinct cells for the broken houses
+        let broken = gen_rand_cells(rng, total_broken, config.rows, config.columns);
+
+        // generating random locations for repairers
+        let repairers = gen_rand_cells(rng, config.repairers, config.rows, config.columns)
+            .into_iter()
+            .enumerate()
+            .map(|(id, location)| RepairerSpec {
+                location,
+                algorithm: config.algorithms[id % config.algorithms.len()].clone(),
+                power: config.repair_power,
+                byzantine: false,
+            })
+            .collect();
+
+        // drawn after the locations so the layout of a seed doesn't depend on the damage range
+        let damage = broken
+            .iter()
+            .map(|_| rng.gen_range(config.damage_range.0..=config.damage_range.1))
+            .collect();
+
+        Self {
+            rows: config.rows,
+            columns: config.columns,
+            seed: None,
+            broken,
+            damage,
+            repairers,
+            aftershocks: Vec::new(),
+            blocked: Vec::new(),
+            terrain: Vec::new(),
+            faults: Vec::new(),
+            aftershock_rate: None,
+            note_noise: None,
+            note_defense: None,
+        }
+    }
+
+    pub fn terrain_map(&self) -> TerrainMap {
+        let mut terrain = vec![vec![Terrain::Road; self.columns as usize]; self.rows as usize];
+        for patch in self.terrain.iter() {
+            terrain[patch.cell.0 as usize][patch.cell.1 as usize] = patch.terrain;
+        }
+        terrain
+    }
+
+    pub fn blocked_cells(&self) -> CellSet {
+        let mut blocked = CellSet::new(self.rows, self.columns);
+        for cell in self.blocked.iter() {
+            blocked.insert(*cell);
+        }
+        blocked
+    }
+
+    // the initial damage of the <index>th broken house
+    pub fn damage_of(&self, index: usize) -> u8 {
+        self.damage.get(index).copied().unwrap_or(MAX_DAMAGE)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.rows < 2 || self.columns < 2 {
+            return Err(ConfigError::GridTooSmall {
+                rows: self.rows,
+                columns: self.columns,
+            });
+        }
+        check_grid_size(self.rows, self.columns)?;
+        let repairers = self.repairers.len() as u32;
+        if !(1..=MAX_REPAIRERS).contains(&repairers) {
+            return Err(ConfigError::RepairersOutOfRange {
+                repairers,
+                max: MAX_REPAIRERS,
+            });
+        }
+        let in_bounds = |cell: &(u32, u32)| cell.0 < self.rows && cell.1 < self.columns;
+        if let Some(cell) = self
+            .blocked
+            .iter()
+            .chain(self.terrain.iter().map(|patch| &patch.cell))
+            .find(|cell| !in_bounds(cell))
+        {
+            return Err(ConfigError::CellOutOfBounds { cell: *cell });
+        }
+        let blocked = self.blocked_cells();
+        if let Some(cell) = self
+            .broken
+            .iter()
+            .chain(self.repairers.iter().map(|r| &r.location))
+            .chain(self.aftershocks.iter().map(|shock| &shock.cell))
+            .chain(self.terrain.iter().map(|patch| &patch.cell))
+            .find(|cell| in_bounds(cell) && blocked.contains(**cell))
+        {
+            return Err(ConfigError::BlockedCell { cell: *cell });
+        }
+        let mut seen: HashSet<(u32, u32)> = HashSet::new();
+        for cell in self.broken.iter() {
+            if !in_bounds(cell) {
+                return Err(ConfigError::CellOutOfBounds { cell: *cell });
+            }
+            if !seen.insert(*cell) {
+                return Err(ConfigError::DuplicateBroken { cell: *cell });
+            }
+        }
+        if let Some(repairer) = self.repairers.iter().find(|r| !in_bounds(&r.location)) {
+            return Err(ConfigError::CellOutOfBounds {
+                cell: repairer.location,
+            });
+        }
+        if !self.damage.is_empty() && self.damage.len() != self.broken.len() {
+            return Err(ConfigError::DamageMismatch {
+                broken: self.broken.len() as u32,
+                damage: self.damage.len() as u32,
+            });
+        }
+        for (cell, damage) in self.broken.iter().zip(self.damage.iter()) {
+            if !(1..=MAX_DAMAGE).contains(damage) {
+                return Err(ConfigError::InvalidDamage {
+                    cell: *cell,
+                    damage: *damage,
+                });
+            }
+        }
+        for shock in self.aftershocks.iter() {
+            if !in_bounds(&shock.cell) {
+                return Err(ConfigError::CellOutOfBounds { cell: shock.cell });
+            }
+            if !(1..=MAX_DAMAGE).contains(&shock.damage) {
+                return Err(ConfigError::InvalidDamage {
+                    cell: shock.cell,
+                    damage: shock.damage,
+                });
+            }
+        }
+        if let Some(repairer) = self
+            .repairers
+            .iter()
+            .find(|r| !(1..=MAX_DAMAGE).contains(&r.power))
+        {
+            return Err(ConfigError::InvalidRepairPower {
+                power: repairer.power,
+            });
+        }
+        if let Some(fault) = self.faults.iter().find(|fault| fault.repairer >= repairers) {
+            return Err(ConfigError::UnknownRepairer {
+                repairer: fault.repairer,
+                repairers,
+            });
+        }
+        Ok(())
+    }
+}
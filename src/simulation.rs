@@ -0,0 +1,1633 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    env, fmt, fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    process,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, ScopedJoinHandle},
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    city::Matrix,
+    coordinator::{Controller, Phase, PhaseTimings, RoundCoordinator, RoundMember},
+    grid::{Grid, Position},
+    models::{
+        CellSet, CityMap, ConfigError, Move, MovementAlgorithm, Note, NoteDefense, Repairer,
+        RepairerResult, SimulationError,
+    },
+    mods::{clear_terminal, execute, label_regions, make_decision, print_matrix},
+    scenario::{Aftershock, Fault, FaultKind, Scenario},
+    strategy::StrategyFactory,
+    trace::Trace,
+    worker::{Order, RepairerProcess, WorkerSetup},
+};
+
+pub const MAX_REPAIRERS: u32 = 64;
+pub const MAX_CELLS: u32 = 1 << 26; // 8192x8192, every cell takes its part of the state array and of the tables of the repairers
+pub const MAX_DAMAGE: u8 = 10; // the damage of a fully broken house, a fine house has no damage
+const FREE_RUN_POLL: Duration = Duration::from_micros(200); // how often the master thread checks on the free running repairers
+const WORLD_STREAM: u64 = 0x0077_6f72_6c64; // sets the aftershock draws apart from the other draws of the seed
+static STATE_FILES: AtomicU32 = AtomicU32::new(0); // the temporary state files created by this process so far
+
+// the default initial algorithms of the repairers
+pub const INIT_ALGOS: [MovementAlgorithm; 8] = [
+    MovementAlgorithm::BRD,
+    MovementAlgorithm::DDL,
+    MovementAlgorithm::BLU,
+    MovementAlgorithm::DUR,
+    MovementAlgorithm::BLD,
+    MovementAlgorithm::DDR,
+    MovementAlgorithm::BRU,
+    MovementAlgorithm::DUL,
+];
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    pub rows: u32,
+    pub columns: u32,
+    pub total_broken: Option<u32>, // when `None` a random amount in <broken_range> is chosen
+    pub broken_range: (u32, u32),  // the inclusive min and max of the random broken houses
+    pub damage_range: (u8, u8), // the inclusive min and max of the random damage of each broken house
+    pub repair_power: u8,       // the damage removed by a single fix of the generated repairers
+    pub repairers: u32,         // between 1 and <MAX_REPAIRERS>
+    pub algorithms: Vec<MovementAlgorithm>, // the initial algorithms, repeated when there is more repairers than algorithms
+    pub seed: Option<u64>, // when `None` a random seed is chosen, runs with the same seed are identical
+    pub frame_delay: Duration, // the sleep between the rendered rounds
+    pub headless: bool,    // skips all of the rendering and the sleeps
+    pub trace: bool,       // records every decision and execution of the run
+    pub scenario: Option<Scenario>, // a fixed layout, replaces the random grid, broken houses and repairers
+    pub strategy: StrategyFactory, // builds the movement strategy of each repairer, the built-in one by default
+    pub share_maps: bool, // publishes the known map of the city next to every note and merges the maps of the others
+    pub aftershock_rate: f64, // the chance of a random house breaking in each round, the repairers don't know the total when there are aftershocks
+    pub max_rounds: Option<u32>, // stops the run after this many rounds
+    pub time_limit: Option<Duration>, // stops the run once the rounds took this long
+    pub quiescence: Option<u32>, // stops the run once the city stayed fully repaired for this many rounds
+    pub on_failure: FailurePolicy, // what happens to the run when a repairer fails
+    pub liars: u32, // marks the first repairers as byzantine, they write false repair counts in their notes
+    pub note_noise: f64, // the chance of any written note carrying a random repair count
+    pub note_defense: NoteDefense, // how the repairers check the counts they read
+    pub mode: ExecutionMode, // the shared rounds or the own clock of every repairer
+    pub state_file: Option<PathBuf>, // places the state of the cells in this memory-mapped file, other processes can watch it
+    pub worker_program: Option<PathBuf>, // the program started with `worker` for each repairer process, needed by the processes mode
+}
+
+impl SimulationConfig {
+    // the settings saved in the scenario fill in the ones left to their defaults, so a saved run replays the same
+    fn with_scenario_settings(self) -> Self {
+        let Some(scenario) = &self.scenario else {
+            return self;
+        };
+        Self {
+            aftershock_rate: match scenario.aftershock_rate {
+                Some(rate) if self.aftershock_rate == 0.0 => rate,
+                _ => self.aftershock_rate,
+            },
+            note_noise: match scenario.note_noise {
+                Some(noise) if self.note_noise == 0.0 => noise,
+                _ => self.note_noise,
+            },
+            note_defense: match scenario.note_defense {
+                Some(defense) if self.note_defense == NoteDefense::default() => defense,
+                _ => self.note_defense,
+            },
+            ..self
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.aftershock_rate) {
+            return Err(ConfigError::InvalidAftershockRate {
+                rate: self.aftershock_rate,
+            });
+        }
+        // the random aftershocks draw their damage from the range
+        if self.damage_range.0 < 1
+            || self.damage_range.0 > self.damage_range.1
+            || self.damage_range.1 > MAX_DAMAGE
+        {
+            return Err(ConfigError::InvalidDamageRange {
+                min: self.damage_range.0,
+                max: self.damage_range.1,
+            });
+        }
+        if !(0.0..=1.0).contains(&self.note_noise) {
+            return Err(ConfigError::InvalidNoteNoise {
+                noise: self.note_noise,
+            });
+        }
+        let repairers = self
+            .scenario
+            .as_ref()
+            .map_or(self.repairers, |s| s.repairers.len() as u32);
+        if self.liars > repairers {
+            return Err(ConfigError::TooManyLiars {
+                liars: self.liars,
+                repairers,
+            });
+        }
+        let open_ended = self.aftershock_rate > 0.0
+            || self
+                .scenario
+                .as_ref()
+                .is_some_and(|s| !s.aftershocks.is_empty());
+        if open_ended
+            && self.max_rounds.is_none()
+            && self.time_limit.is_none()
+            && self.quiescence.is_none()
+        {
+            return Err(ConfigError::NoTermination);
+        }
+        if let Some(rounds) = self.max_rounds.filter(|rounds| *rounds > Note::MAX_ROUND) {
+            return Err(ConfigError::TooManyRounds {
+                rounds,
+                max: Note::MAX_ROUND,
+            });
+        }
+        if self.mode == ExecutionMode::FreeRunning {
+            let faults = self.scenario.as_ref().is_some_and(|s| !s.faults.is_empty());
+            let feature = match (open_ended, faults, self.trace) {
+                (true, _, _) => Some("aftershocks"),
+                (_, true, _) => Some("faults"),
+                (_, _, true) => Some("traces"),
+                _ => None,
+            };
+            if let Some(feature) = feature {
+                return Err(ConfigError::NeedsLockstep { feature });
+            }
+        }
+        if self.mode == ExecutionMode::Processes && self.share_maps {
+            return Err(ConfigError::NeedsThreads {
+                feature: "shared maps",
+            });
+        }
+        // an embedding program isn't a repairer process, it has to name one
+        if self.mode == ExecutionMode::Processes && self.worker_program.is_none() {
+            return Err(ConfigError::MissingWorkerProgram);
+        }
+        if let Some(scenario) = &self.scenario {
+            return scenario.validate();
+        }
+        if self.rows < 2 || self.columns < 2 {
+            return Err(ConfigError::GridTooSmall {
+                rows: self.rows,
+                columns: self.columns,
+            });
+        }
+        if !(1..=MAX_REPAIRERS).contains(&self.repairers) {
+            return Err(ConfigError::RepairersOutOfRange {
+                repairers: self.repairers,
+                max: MAX_REPAIRERS,
+            });
+        }
+        if self.algorithms.is_empty() {
+            return Err(ConfigError::NoAlgorithms);
+        }
+        if !(1..=MAX_DAMAGE).contains(&self.repair_power) {
+            return Err(ConfigError::InvalidRepairPower {
+                power: self.repair_power,
+            });
+        }
+        let max_broken = check_grid_size(self.rows, self.columns)?;
+        // each broken house takes a distinct cell
+        match self.total_broken {
+            Some(broken) if broken > max_broken => Err(ConfigError::TooManyBroken {
+                broken,
+                max: max_broken,
+            }),
+            Some(_) => Ok(()),
+            None if self.broken_range.0 > self.broken_range.1 => {
+                Err(ConfigError::InvalidBrokenRange {
+                    min: self.broken_range.0,
+                    max: self.broken_range.1,
+                })
+            }
+            None if self.broken_range.1 > max_broken => Err(ConfigError::TooManyBroken {
+                broken: self.broken_range.1,
+                max: max_broken,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            rows: 7,
+            columns: 7,
+            total_broken: None,
+            broken_range: (3, 6),
+            damage_range: (MAX_DAMAGE, MAX_DAMAGE),
+            repair_power: MAX_DAMAGE,
+            repairers: 4,
+            algorithms: INIT_ALGOS.to_vec(),
+            seed: None,
+            frame_delay: Duration::from_millis(50),
+            headless: false,
+            trace: false,
+            scenario: None,
+            strategy: StrategyFactory::default(),
+            share_maps: false,
+            aftershock_rate: 0.0,
+            max_rounds: None,
+            time_limit: None,
+            quiescence: None,
+            on_failure: FailurePolicy::Abort,
+            liars: 0,
+            note_noise: 0.0,
+            note_defense: NoteDefense::Trust,
+            mode: ExecutionMode::Lockstep,
+            state_file: None,
+            worker_program: None,
+        }
+    }
+}
+
+// how the repairer threads are driven
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionMode {
+    Lockstep, // every round is decided and executed by all of the repairers together, behind the barriers
+    FreeRunning, // every repairer decides and executes on its own clock against the shared matrix
+    Processes, // the lockstep rounds with every repairer in its own process on the state file, always with the built-in strategy
+}
+
+impl FromStr for ExecutionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lockstep" => Ok(Self::Lockstep),
+            "free-running" => Ok(Self::FreeRunning),
+            "processes" => Ok(Self::Processes),
+            _ => Err(format!(
+                "unknown execution mode `{}`, expected one of lockstep, free-running, processes",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lockstep => write!(f, "lockstep"),
+            Self::FreeRunning => write!(f, "free-running"),
+            Self::Processes => write!(f, "processes"),
+        }
+    }
+}
+
+// what the supervisor does once a repairer has failed, the failed repairer is out of the run either way
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailurePolicy {
+    Abort,    // stops the run and returns the failure
+    Continue, // reports the failure and carries on with the surviving repairers
+}
+
+impl FromStr for FailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "abort" => Ok(Self::Abort),
+            "continue" => Ok(Self::Continue),
+            _ => Err(format!(
+                "unknown failure policy `{}`, expected one of abort, continue",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FailurePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Abort => write!(f, "abort"),
+            Self::Continue => write!(f, "continue"),
+        }
+    }
+}
+
+// why a run has ended
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Repaired,   // every repairer has reached the end of its explore
+    RoundLimit, // <max_rounds> was reached
+    TimeLimit,  // <time_limit> was reached
+    Quiescent,  // the city stayed fully repaired for <quiescence> rounds
+    Failed, // a repairer failed and the run was stopped, or every repairer failed before the city was repaired
+    Premature, // every repairer has ended its explore with houses still broken, misled by false notes
+    Aborted,   // the run was aborted through its controller
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Repaired => write!(f, "repaired"),
+            Self::RoundLimit => write!(f, "round_limit"),
+            Self::TimeLimit => write!(f, "time_limit"),
+            Self::Quiescent => write!(f, "quiescent"),
+            Self::Failed => write!(f, "failed"),
+            Self::Premature => write!(f, "premature"),
+            Self::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulationResult {
+    pub seed: u64,
+    pub rounds: u32,
+    pub outcome: Outcome,
+    pub wall_time_ms: f64, // the time spent in the rounds, rendering included
+    pub goal: u32, // the broken houses the repairers can reach, including the ones broken by the aftershocks
+    pub unreachable: Vec<(u32, u32)>, // the broken houses walled off from every repairer by the blocked cells
+    pub broken_left: u32,             // the reachable houses still broken at the end of the run
+    pub decide_ms: f64,               // the time of the decision phases, the lockstep runs only
+    pub execute_ms: f64,              // the time of the execution phases, the lockstep runs only
+    pub total_repairs: u32,           // the restored houses
+    pub total_repair_actions: u32, // every fix, several of them are needed for a heavily damaged house
+    pub total_moves: u32,
+    pub total_travel_cost: u32,
+    pub repairers: Vec<RepairerResult>,
+    pub failures: Vec<SimulationError>, // the repairers the run has carried on without
+    pub faults: Vec<Fault>, // the injected faults, the ones of the repairers already done are left out
+    #[serde(skip)]
+    pub trace: Option<Trace>,
+}
+
+impl SimulationResult {
+    pub const CSV_HEADER: &'static str = "seed,rounds,outcome,wall_time_ms,goal,total_repairs,total_repair_actions,total_moves,total_travel_cost,repairer,repairs,repair_actions,moves,travel_cost,final_row,final_column,known_repairs,explored,unreachable,failed,repairs_after_faults,broken_left,byzantine,corrupted_notes,rejected_notes,premature,decide_ms,execute_ms";
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    // one row per repairer, the run level columns are repeated on every row
+    pub fn to_csv_rows(&self) -> Vec<String> {
+        self.repairers
+            .iter()
+            .map(|repairer| {
+                format!(
+                    "{},{},{},{:.3},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{:.3}",
+                    self.seed,
+                    self.rounds,
+                    self.outcome,
+                    self.wall_time_ms,
+                    self.goal,
+                    self.total_repairs,
+                    self.total_repair_actions,
+                    self.total_moves,
+                    self.total_travel_cost,
+                    repairer.id,
+                    repairer.repairs,
+                    repairer.repair_actions,
+                    repairer.moves,
+                    repairer.travel_cost,
+                    repairer.final_location.row,
+                    repairer.final_location.col,
+                    repairer
+                        .known_repairs
+                        .iter()
+                        .map(|(id, repairs)| format!("{}:{}", id, repairs))
+                        .collect::<Vec<String>>()
+                        .join(";"),
+                    repairer.explored,
+                    self.unreachable.len(),
+                    self.failures
+                        .iter()
+                        .any(|failure| failure.repairer() == repairer.id),
+                    repairer
+                        .repairs_after_faults
+                        .map(|repairs| repairs.to_string())
+                        .unwrap_or_default(),
+                    self.broken_left,
+                    repairer.byzantine,
+                    repairer.corrupted_notes,
+                    repairer.rejected_notes,
+                    repairer.premature,
+                    self.decide_ms,
+                    self.execute_ms,
+                )
+            })
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = Self::CSV_HEADER.to_string();
+        for row in self.to_csv_rows() {
+            csv.push('\n');
+            csv.push_str(&row);
+        }
+        csv
+    }
+}
+
+// what a run starts from, before the state of its cells is laid out
+struct Setup {
+    seed: u64,
+    scenario: Scenario,
+    world_rng: StdRng,
+    grid: Grid,
+    repairer_locations: Vec<Position>,
+    blocked: Arc<CellSet>,
+    reachable: CellSet,
+    unreachable: Vec<(u32, u32)>,
+    total_broken: u32,
+    repairers: Vec<Repairer>,
+}
+
+pub struct Simulation {
+    matrix: Arc<Matrix>,
+    repairers_state: Vec<Arc<Mutex<Repairer>>>,
+    coordinator: RoundCoordinator, // the phases of the lockstep rounds and the control messages of the run
+    // @param dead_repairers will be used to check the end of the repairing progress.
+    dead_repairers: Arc<Vec<AtomicBool>>,
+    frame_delay: Duration,
+    headless: bool,
+    seed: u64,
+    scenario: Scenario,
+    rounds: u32,
+    elapsed: Duration,
+    world_rng: StdRng, // drives the random aftershocks
+    aftershock_rate: f64,
+    damage_range: (u8, u8),
+    max_rounds: Option<u32>,
+    time_limit: Option<Duration>,
+    quiescence: Option<u32>,
+    quiet_rounds: u32, // the rounds the city has been fully repaired in a row
+    goal: u32,         // the reachable houses broken so far
+    blocked: Arc<CellSet>,
+    reachable: CellSet, // the cells of the regions holding at least one repairer
+    unreachable: Vec<(u32, u32)>, // the broken houses out of the reachable cells
+    aftershocks: Vec<Aftershock>, // the aftershocks applied so far, scheduled or random
+    outcome: Option<Outcome>,
+    on_failure: FailurePolicy,
+    failures: Vec<SimulationError>, // the failed repairers when the run carries on without them
+    faults: Vec<Fault>,             // the faults injected so far
+    fixed_before_faults: Option<Vec<u32>>, // the repairs of each repairer when the first fault was injected
+    premature: Vec<bool>, // the repairers that ended their explore with houses still broken
+    mode: ExecutionMode,
+    worker_program: Option<PathBuf>,
+    worker_setup: Option<WorkerSetup>, // what every repairer process is started with, only in the processes mode
+    temporary_state: Option<PathBuf>, // the state file created for the repairer processes, removed with the simulation
+    trace: Option<Trace>,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Result<Self, ConfigError> {
+        let config = config.with_scenario_settings();
+        config.validate()?;
+        if !config.headless {
+            clear_terminal();
+        }
+
+        let Setup {
+            seed,
+            scenario,
+            world_rng,
+            grid,
+            repairer_locations,
+            blocked,
+            reachable,
+            unreachable,
+            total_broken,
+            repairers,
+        } = Self::setup(&config)?;
+        let repairers_count = repairers.len() as u32;
+        // the repairer processes can only share a state file
+        let temporary_state = match (&config.state_file, config.mode) {
+            (None, ExecutionMode::Processes) => Some(env::temp_dir().join(format!(
+                "arvan_test-{}-{}.state",
+                process::id(),
+                STATE_FILES.fetch_add(1, Ordering::Relaxed)
+            ))),
+            _ => None,
+        };
+        let state_file = config.state_file.clone().or(temporary_state.clone());
+        // Creating the matrix, one note slot per repairer on every house
+        let matrix = match &state_file {
+            Some(path) => {
+                Matrix::map(path, grid, repairers_count, config.share_maps).map_err(|e| {
+                    ConfigError::StateFile {
+                        path: path.clone(),
+                        message: e.to_string(),
+                    }
+                })?
+            }
+            None => Matrix::new(grid, repairers_count, config.share_maps).map_err(|e| {
+                ConfigError::OutOfMemory {
+                    message: e.to_string(),
+                }
+            })?,
+        };
+        for cell in blocked.iter() {
+            matrix.house(cell.into()).block();
+        }
+        for (id, location) in repairer_locations.iter().enumerate() {
+            matrix.set_location(id as u32, *location);
+        }
+        // this matrix is for reading, the arc lets the thread to read the data and not lock it.
+        let matrix: Arc<Matrix> = Arc::new(matrix);
+
+        if !config.headless {
+            println!("initial matrix ");
+            print_matrix(
+                &matrix,
+                &blocked,
+                &vec![Position::default(); repairers_count as usize],
+            );
+            thread::sleep(Duration::from_secs(1));
+        }
+
+        // adding the broken houses, the value of the broken elements is their damage while the normal ones are 0.
+        for (idx, (row_idx, col_idx)) in scenario.broken.iter().enumerate() {
+            matrix.break_house(Position::new(*row_idx, *col_idx), scenario.damage_of(idx));
+        }
+
+        if !config.headless {
+            println!("adding broken houses ...");
+            thread::sleep(Duration::from_secs(1));
+            print_matrix(&matrix, &blocked, &repairer_locations);
+        }
+        let repairers_state: Vec<Arc<Mutex<Repairer>>> = repairers
+            .into_iter()
+            .map(|repairer| Arc::new(Mutex::new(repairer)))
+            .collect();
+
+        // this variable will be set for each thread that reaches to the Move `None`; when all are set the matrix is fully repaired.
+        let dead_repairers: Arc<Vec<AtomicBool>> = Arc::new(
+            (0..repairers_count)
+                .map(|_| AtomicBool::new(false))
+                .collect(),
+        );
+
+        // the repairer threads are spawned once the run starts, each of them gets its own end of the round channels
+        let coordinator = RoundCoordinator::new(repairers_count);
+
+        Ok(Self {
+            matrix,
+            repairers_state,
+            coordinator,
+            dead_repairers,
+            frame_delay: config.frame_delay,
+            headless: config.headless,
+            seed,
+            rounds: 0,
+            elapsed: Duration::ZERO,
+            world_rng,
+            aftershock_rate: config.aftershock_rate,
+            damage_range: config.damage_range,
+            // an unbounded run still ends before its rounds outgrow the notes
+            max_rounds: Some(config.max_rounds.unwrap_or(Note::MAX_ROUND)),
+            time_limit: config.time_limit,
+            quiescence: config.quiescence,
+            quiet_rounds: 0,
+            goal: total_broken,
+            blocked,
+            reachable,
+            unreachable,
+            aftershocks: Vec::new(),
+            outcome: None,
+            on_failure: config.on_failure,
+            failures: Vec::new(),
+            faults: Vec::new(),
+            fixed_before_faults: None,
+            premature: vec![false; repairers_count as usize],
+            mode: config.mode,
+            worker_program: config.worker_program,
+            worker_setup: match (config.mode, state_file) {
+                (ExecutionMode::Processes, Some(state_file)) => Some(WorkerSetup {
+                    id: 0,
+                    state_file,
+                    scenario: scenario.clone(),
+                    aftershock_rate: config.aftershock_rate,
+                    note_noise: config.note_noise,
+                    note_defense: config.note_defense,
+                }),
+                _ => None,
+            },
+            temporary_state,
+            trace: if config.trace {
+                Some(Trace::new(scenario.clone()))
+            } else {
+                None
+            },
+            scenario,
+        })
+    }
+
+    // the initial state of a single repairer of the run, a repairer process rebuilds its own state with it
+    // the config has been validated by the master process already
+    pub fn repairer(config: &SimulationConfig, id: u32) -> Result<Repairer, ConfigError> {
+        let config = &config.clone().with_scenario_settings();
+        let mut repairers = Self::setup(config)?.repairers;
+        match id < repairers.len() as u32 {
+            true => Ok(repairers.swap_remove(id as usize)),
+            false => Err(ConfigError::UnknownRepairer {
+                repairer: id,
+                repairers: repairers.len() as u32,
+            }),
+        }
+    }
+
+    // the layout of the run and the initial state of its repairers, everything of it is derived from the config and the seed
+    fn setup(config: &SimulationConfig) -> Result<Setup, ConfigError> {
+        // every random draw of the run comes from this seed
+        let seed = config.seed.unwrap_or_else(|| {
+            config
+                .scenario
+                .as_ref()
+                .and_then(|s| s.seed)
+                .unwrap_or_else(|| rand::thread_rng().gen())
+        });
+        let mut rng = StdRng::seed_from_u64(seed);
+        // drawn before the layout so a saved scenario replays the same decisions
+        let mut decisions_rng = StdRng::seed_from_u64(rng.gen());
+        // a stream of its own, a saved scenario skips the layout draws and still replays the same aftershocks
+        let world_rng = StdRng::seed_from_u64(seed ^ WORLD_STREAM);
+
+        let mut scenario = match &config.scenario {
+            Some(scenario) => scenario.clone(),
+            None => Scenario::generate(config, &mut rng),
+        };
+        scenario.seed = Some(seed);
+        scenario.aftershock_rate = (config.aftershock_rate > 0.0).then_some(config.aftershock_rate);
+        scenario.note_noise = (config.note_noise > 0.0).then_some(config.note_noise);
+        scenario.note_defense =
+            (config.note_defense != NoteDefense::default()).then_some(config.note_defense);
+        for spec in scenario.repairers.iter_mut().take(config.liars as usize) {
+            spec.byzantine = true;
+        }
+        let open_ended = config.aftershock_rate > 0.0 || !scenario.aftershocks.is_empty();
+
+        let rows = scenario.rows;
+        let columns = scenario.columns;
+        let grid = Grid::new(rows, columns);
+        let repairers_count = scenario.repairers.len() as u32;
+        let repairer_locations: Vec<Position> = scenario
+            .repairers
+            .iter()
+            .map(|r| grid.position(r.location))
+            .collect::<Result<_, _>>()?;
+
+        // the repairers only know about the broken houses of their own region, the rest are reported as unreachable
+        let blocked = Arc::new(scenario.blocked_cells());
+        let terrain = Arc::new(scenario.terrain_map());
+        let regions = label_regions(&grid, &blocked);
+        let region_of = |position: Position| regions[position.row as usize][position.col as usize];
+        let staffed: HashSet<Option<u32>> =
+            repairer_locations.iter().map(|l| region_of(*l)).collect();
+        let mut reachable = CellSet::new(rows, columns);
+        for position in grid.positions() {
+            if staffed.contains(&region_of(position)) {
+                reachable.insert(position.into());
+            }
+        }
+        let (reachable_broken, unreachable): (Vec<_>, Vec<_>) = scenario
+            .broken
+            .iter()
+            .partition(|cell| reachable.contains(**cell));
+        let total_broken = reachable_broken.len() as u32;
+
+        // creating the repairers state
+        let mut repairers: Vec<Repairer> = Vec::new();
+
+        for id in 0..repairers_count {
+            let repairs_track: HashMap<u32, u32> =
+                (0..repairers_count).map(|other| (other, 0)).collect();
+
+            let tmp_repairer = Repairer {
+                id,
+                thread: None,
+                total_broken: if open_ended {
+                    None
+                } else {
+                    Some(
+                        reachable_broken
+                            .iter()
+                            .filter(|cell| {
+                                region_of(Position::from(**cell))
+                                    == region_of(repairer_locations[id as usize])
+                            })
+                            .count() as u32,
+                    )
+                },
+                blocked: Arc::clone(&blocked),
+                terrain: Arc::clone(&terrain),
+                busy_rounds: 0,
+                travel_cost: 0,
+                total_fixed: 0,
+                repair_actions: 0,
+                repair_power: scenario.repairers[id as usize].power,
+                other_repairers_repairs: repairs_track,
+                total_moves: 0,
+                strategy: config.strategy.build(id, &scenario.repairers[id as usize]),
+                current_location: repairer_locations[id as usize],
+                grid,
+                decision: Move::Empty,
+                move_turn: true, // means the first move
+                rng: StdRng::seed_from_u64(decisions_rng.gen()), // one derived rng per repairer
+                round: 0,
+                last_event: None,
+                failure: None,
+                reported: None,
+                map: CityMap::new(rows, columns),
+                share_maps: config.share_maps,
+                byzantine: scenario.repairers[id as usize].byzantine,
+                note_noise: config.note_noise,
+                defense: config.note_defense,
+                known_rounds: HashMap::new(),
+                corrupted_notes: 0,
+                rejected_notes: 0,
+                lockstep: config.mode != ExecutionMode::FreeRunning,
+            };
+            repairers.push(tmp_repairer)
+        }
+
+        Ok(Setup {
+            seed,
+            scenario,
+            world_rng,
+            grid,
+            repairer_locations,
+            blocked,
+            reachable,
+            unreachable,
+            total_broken,
+            repairers,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+            || self
+                .dead_repairers
+                .iter()
+                .all(|dead| dead.load(Ordering::Relaxed))
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    // ends the run before the repairers are done, the live repairers are told to leave their loops
+    fn stop(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+        self.coordinator.close();
+    }
+
+    // pauses, resumes or aborts the run from another thread
+    pub fn controller(&self) -> Controller {
+        self.coordinator.controller()
+    }
+
+    // the time spent in each phase of the lockstep rounds so far
+    pub fn phase_timings(&self) -> PhaseTimings {
+        self.coordinator.timings()
+    }
+
+    // breaks the houses of the scheduled aftershocks of this round and maybe a random one, returns whether anything broke
+    fn apply_aftershocks(&mut self) -> bool {
+        let mut shocks: Vec<Aftershock> = self
+            .scenario
+            .aftershocks
+            .iter()
+            .filter(|shock| shock.round == self.rounds)
+            .cloned()
+            .collect();
+        if self.aftershock_rate > 0.0 && self.world_rng.gen_bool(self.aftershock_rate) {
+            shocks.push(Aftershock {
+                round: self.rounds,
+                cell: (
+                    self.world_rng.gen_range(0..self.scenario.rows),
+                    self.world_rng.gen_range(0..self.scenario.columns),
+                ),
+                damage: self
+                    .world_rng
+                    .gen_range(self.damage_range.0..=self.damage_range.1),
+            });
+        }
+
+        // the rubble of the blocked cells can't break any further
+        shocks.retain(|shock| !self.blocked.contains(shock.cell));
+        for shock in shocks.iter() {
+            if shock.strike(&self.matrix) {
+                if self.reachable.contains(shock.cell) {
+                    self.goal += 1;
+                } else {
+                    self.unreachable.push(shock.cell);
+                }
+            }
+        }
+        let shaken = !shocks.is_empty();
+        self.aftershocks.extend(shocks);
+        shaken
+    }
+
+    // injects the scheduled faults of this round, a stopped repairer leaves the run like a done one
+    fn apply_faults(&mut self) {
+        let faults: Vec<Fault> = self
+            .scenario
+            .faults
+            .iter()
+            .filter(|fault| fault.round == self.rounds)
+            .cloned()
+            .collect();
+        for fault in faults {
+            let id = fault.repairer as usize;
+            if self.dead_repairers[id].load(Ordering::Relaxed) {
+                continue;
+            }
+            if self.fixed_before_faults.is_none() {
+                self.fixed_before_faults = Some(
+                    self.repairers_state
+                        .iter()
+                        .map(|state| state.lock().unwrap().total_fixed)
+                        .collect(),
+                );
+            }
+            let mut repairer = self.repairers_state[id].lock().unwrap();
+            match fault.kind {
+                FaultKind::Stop => {
+                    // out of the phases of this round already
+                    self.dead_repairers[id].store(true, Ordering::Relaxed);
+                    self.coordinator.dismiss(fault.repairer);
+                }
+                FaultKind::Pause { rounds } => repairer.busy_rounds += rounds,
+                FaultKind::Amnesia => repairer.forget(),
+                // the proxy of a repairer process kills it for real
+                FaultKind::Crash if self.mode == ExecutionMode::Processes => {}
+                FaultKind::Crash => {
+                    self.dead_repairers[id].store(true, Ordering::Relaxed);
+                    self.coordinator.dismiss(fault.repairer);
+                    repairer.failure = Some(SimulationError::Crashed {
+                        repairer: fault.repairer,
+                        round: self.rounds,
+                        message: "injected fault".to_string(),
+                    });
+                }
+            }
+            drop(repairer);
+            self.faults.push(fault);
+        }
+    }
+
+    // the unreachable houses are left out, nobody can repair them so all of them are still broken
+    fn broken_left(&self) -> u32 {
+        self.matrix.broken_houses() - self.unreachable.len() as u32
+    }
+
+    fn is_city_repaired(&self) -> bool {
+        self.broken_left() == 0
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // the layout of this run, including the seed, saving it allows reproducing the run later
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    pub fn render(&self) {
+        let indexes: Vec<Position> = self
+            .repairers_state
+            .iter()
+            .map(|state| state.lock().unwrap().current_location)
+            .collect();
+        print_matrix(&self.matrix, &self.blocked, &indexes);
+    }
+
+    // runs a single decision making + execution round, returns false once all of the repairers are done.
+    // the lockstep and the processes repairers take part in it, it is stepped through `Stepper::step`
+    fn run_round(&mut self) -> Result<bool, SimulationError> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        if self.coordinator.aborted() {
+            self.stop(Outcome::Aborted);
+            return Ok(false);
+        }
+        if self.max_rounds.is_some_and(|max| self.rounds >= max) {
+            self.stop(Outcome::RoundLimit);
+            return Ok(false);
+        }
+        if self.time_limit.is_some_and(|limit| self.elapsed >= limit) {
+            self.stop(Outcome::TimeLimit);
+            return Ok(false);
+        }
+        let started = Instant::now();
+        let shaken = self.apply_aftershocks();
+        self.apply_faults();
+        let done_before: Vec<bool> = self
+            .dead_repairers
+            .iter()
+            .map(|dead| dead.load(Ordering::Relaxed))
+            .collect();
+
+        if !self.headless {
+            clear_terminal();
+            self.render();
+
+            thread::sleep(self.frame_delay);
+        }
+
+        // Faze one: decision making, every live repairer decides against the same matrix
+        // Faze two: executing, all of them start together once every decision is made and the round ends with the last one
+        let completed = self
+            .coordinator
+            .run_phase(Phase::Decide, self.rounds)
+            .and_then(|_| self.coordinator.run_phase(Phase::Execute, self.rounds));
+        if completed.is_none() {
+            self.elapsed += started.elapsed();
+            self.stop(Outcome::Aborted);
+            return Ok(false);
+        }
+
+        if let Some(trace) = self.trace.as_mut() {
+            trace.rounds.push(
+                self.repairers_state
+                    .iter()
+                    .filter_map(|state| state.lock().unwrap().last_event.take())
+                    .collect(),
+            );
+        }
+
+        self.rounds += 1;
+        self.matrix.set_round(self.rounds);
+        self.elapsed += started.elapsed();
+
+        // the failed repairers have left their loops and are already out of the next phases
+        let failures: Vec<SimulationError> = self
+            .repairers_state
+            .iter()
+            .filter_map(|state| state.lock().unwrap().failure.take())
+            .collect();
+        if let Some(error) = failures.first() {
+            if self.on_failure == FailurePolicy::Abort {
+                self.stop(Outcome::Failed);
+                return Err(error.clone());
+            }
+            self.failures.extend(failures);
+        }
+
+        // the explores ended in this round while houses are still broken, the failed repairers are reported already
+        if !self.is_city_repaired() {
+            for (id, dead) in self.dead_repairers.iter().enumerate() {
+                if !done_before[id]
+                    && dead.load(Ordering::Relaxed)
+                    && !self.failures.iter().any(|f| f.repairer() == id as u32)
+                {
+                    self.premature[id] = true;
+                }
+            }
+        }
+
+        if let Some(quiescence) = self.quiescence {
+            if !shaken && self.is_city_repaired() {
+                self.quiet_rounds += 1;
+            } else {
+                self.quiet_rounds = 0;
+            }
+            if self.quiet_rounds >= quiescence {
+                self.stop(Outcome::Quiescent);
+            }
+        }
+        if self.outcome.is_none() && self.is_finished() {
+            self.settle_outcome();
+        }
+        Ok(!self.is_finished())
+    }
+
+    // the outcome of a run whose repairers are all done
+    fn settle_outcome(&mut self) {
+        // the survivors may have been done before the houses of the failed or stopped ones were counted
+        // otherwise only false notes can end the explores with houses still broken
+        let broken_down = !self.failures.is_empty() || !self.faults.is_empty();
+        self.outcome = Some(match self.is_city_repaired() {
+            true => Outcome::Repaired,
+            false if broken_down => Outcome::Failed,
+            false => Outcome::Premature,
+        });
+    }
+
+    // the repairers loop on their own clocks, the master thread only watches the limits and the failures
+    // every repairer stops by itself at the round limit
+    fn run_free(&mut self, repairers: &[ScopedJoinHandle<'_, ()>]) -> Result<(), SimulationError> {
+        let started = Instant::now();
+        let result = loop {
+            if self.headless {
+                thread::sleep(FREE_RUN_POLL);
+            } else {
+                clear_terminal();
+                self.render();
+                thread::sleep(self.frame_delay);
+            }
+            // checked before the failures so the failure of an ended repairer is never missed
+            let ended = repairers.iter().all(|handle| handle.is_finished());
+            self.rounds = self
+                .repairers_state
+                .iter()
+                .map(|state| lock_watched(state).round + 1)
+                .max()
+                .unwrap_or(0);
+            self.matrix.set_round(self.rounds);
+
+            let failures: Vec<SimulationError> = self
+                .repairers_state
+                .iter()
+                .filter_map(|state| lock_watched(state).failure.take())
+                .collect();
+            if let Some(error) = failures.first() {
+                if self.on_failure == FailurePolicy::Abort {
+                    self.outcome = Some(Outcome::Failed);
+                    break Err(error.clone());
+                }
+                self.failures.extend(failures);
+            }
+
+            if self.coordinator.aborted() {
+                self.outcome = Some(Outcome::Aborted);
+                break Ok(());
+            }
+            if ended {
+                // the repairers that aren't done have reached the round limit
+                match self.is_finished() {
+                    true => self.settle_outcome(),
+                    false => self.outcome = Some(Outcome::RoundLimit),
+                }
+                break Ok(());
+            }
+            if self
+                .time_limit
+                .is_some_and(|limit| self.elapsed + started.elapsed() >= limit)
+            {
+                self.outcome = Some(Outcome::TimeLimit);
+                break Ok(());
+            }
+        };
+
+        self.elapsed += started.elapsed();
+        result
+    }
+
+    // starts a process per repairer on the state file, none of them outside of the processes mode
+    fn spawn_processes(&self) -> Result<Vec<RepairerProcess>, SimulationError> {
+        let Some(setup) = &self.worker_setup else {
+            return Ok(Vec::new());
+        };
+        let program = self
+            .worker_program
+            .as_ref()
+            .expect("the worker program is checked by the validation");
+        (0..self.repairers_state.len() as u32)
+            .map(|id| {
+                RepairerProcess::spawn(
+                    program,
+                    &WorkerSetup {
+                        id,
+                        ..setup.clone()
+                    },
+                )
+                .map_err(|e| SimulationError::Crashed {
+                    repairer: id,
+                    round: 0,
+                    message: e.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    // runs the rounds until the end of the run, see `run_with` and `finish`
+    pub fn run_to_completion(mut self) -> Result<SimulationResult, SimulationError> {
+        self.run_with(|stepper| loop {
+            match stepper.step() {
+                Ok(true) => {}
+                Ok(false) => break Ok(()),
+                Err(error) => break Err(error),
+            }
+        })??;
+        Ok(self.finish())
+    }
+
+    // the single entry point of the rounds, the repairer threads are scoped to it and borrow the matrix and their own state
+    // it spawns them for the mode of the run and hands the rounds to <drive>, one `Stepper::step` at a time
+    // the run ends when <drive> returns: the repairers leave and are joined, an unfinished run ends as aborted
+    // a later call finds the run over and its steps return false, the result is taken with `finish`
+    pub fn run_with<T>(
+        &mut self,
+        drive: impl FnOnce(&mut Stepper<'_, '_>) -> T,
+    ) -> Result<T, SimulationError> {
+        let matrix = Arc::clone(&self.matrix);
+        let states = self.repairers_state.clone();
+        let checks = Arc::clone(&self.dead_repairers);
+        let members = self.coordinator.take_members();
+        let max_rounds = self.max_rounds;
+        let faults = self.scenario.faults.clone();
+        // the repairers of an ended run have left already, there are no processes to start for them
+        let mut processes = match self.outcome {
+            Some(_) => Vec::new(),
+            None => self.spawn_processes()?,
+        }
+        .into_iter();
+
+        let result = thread::scope(|scope| {
+            let repairers: Vec<ScopedJoinHandle<'_, ()>> = members
+                .into_iter()
+                .zip(states.iter())
+                .map(|(member, state)| {
+                    let (matrix, checks) = (&*matrix, &checks[..]);
+                    match self.mode {
+                        ExecutionMode::Lockstep => {
+                            scope.spawn(move || run_lockstep(&member, state, matrix, checks))
+                        }
+                        ExecutionMode::FreeRunning => scope.spawn(move || {
+                            if let Err(error) = run_free(&member, state, matrix, checks, max_rounds)
+                            {
+                                fail(state, checks, error);
+                            }
+                        }),
+                        ExecutionMode::Processes => {
+                            let process = processes.next().expect("a process per repairer");
+                            let faults = &faults[..];
+                            scope
+                                .spawn(move || run_process(&member, state, checks, process, faults))
+                        }
+                    }
+                })
+                .collect();
+
+            let result = drive(&mut Stepper {
+                simulation: &mut *self,
+                repairers: &repairers,
+            });
+            // the repairers still waiting on a phase or a step leave their loops before the scope joins them
+            self.coordinator.close();
+            result
+        });
+        // the watchers of the state file see the end of the run, the file itself is written back once more
+        self.matrix.set_finished();
+        let _ = self.matrix.flush();
+        if self.outcome.is_none() && !self.is_finished() {
+            self.stop(Outcome::Aborted);
+        }
+        Ok(result)
+    }
+
+    // the result of the run, once it is over
+    pub fn finish(mut self) -> SimulationResult {
+        // the repairers don't know the total when there are aftershocks
+        let repairers: Vec<RepairerResult> = self
+            .repairers_state
+            .iter()
+            .map(|state| {
+                let repairer = state.lock().unwrap();
+                RepairerResult {
+                    goal: repairer.total_broken.unwrap_or(self.goal),
+                    repairs_after_faults: self
+                        .fixed_before_faults
+                        .as_ref()
+                        .map(|fixed| repairer.total_fixed - fixed[repairer.id as usize]),
+                    premature: self.premature[repairer.id as usize],
+                    ..repairer.to_result()
+                }
+            })
+            .collect();
+
+        // the trace replays the random aftershocks as scheduled ones
+        if let Some(trace) = self.trace.as_mut() {
+            trace.scenario.aftershocks = self.aftershocks.clone();
+            trace.scenario.aftershock_rate = None;
+        }
+
+        SimulationResult {
+            seed: self.seed,
+            rounds: self.rounds,
+            outcome: self.outcome.unwrap_or(Outcome::Repaired),
+            wall_time_ms: self.elapsed.as_secs_f64() * 1000.0,
+            goal: self.goal,
+            unreachable: self.unreachable.clone(),
+            broken_left: self.broken_left(),
+            decide_ms: self.phase_timings().decide.as_secs_f64() * 1000.0,
+            execute_ms: self.phase_timings().execute.as_secs_f64() * 1000.0,
+            total_repairs: repairers.iter().map(|r| r.repairs).sum(),
+            total_repair_actions: repairers.iter().map(|r| r.repair_actions).sum(),
+            total_moves: repairers.iter().map(|r| r.moves).sum(),
+            total_travel_cost: repairers.iter().map(|r| r.travel_cost).sum(),
+            repairers,
+            failures: self.failures.clone(),
+            faults: self.faults.clone(),
+            trace: self.trace.take(),
+        }
+    }
+}
+
+// the running rounds of a simulation, handed to the driver of `Simulation::run_with` while the repairers are up
+// it only lives as long as the scope of the repairer threads, stepping a run is done through it
+pub struct Stepper<'a, 'scope> {
+    simulation: &'a mut Simulation,
+    repairers: &'a [ScopedJoinHandle<'scope, ()>],
+}
+
+impl Stepper<'_, '_> {
+    // runs a single decision making + execution round, returns false once all of the repairers are done
+    // the free running repairers have no shared rounds to step through, a single step runs them to the end
+    pub fn step(&mut self) -> Result<bool, SimulationError> {
+        match self.simulation.mode {
+            ExecutionMode::Lockstep | ExecutionMode::Processes => self.simulation.run_round(),
+            ExecutionMode::FreeRunning => {
+                if !self.simulation.is_finished() {
+                    self.simulation.run_free(self.repairers)?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    // the state of the run between the steps, for rendering or checking it
+    pub fn simulation(&self) -> &Simulation {
+        self.simulation
+    }
+}
+
+// the amount of cells of a grid, an error when there are more than <MAX_CELLS>
+pub fn check_grid_size(rows: u32, columns: u32) -> Result<u32, ConfigError> {
+    rows.checked_mul(columns)
+        .filter(|cells| *cells <= MAX_CELLS)
+        .ok_or(ConfigError::GridTooLarge {
+            rows,
+            columns,
+            max: MAX_CELLS,
+        })
+}
+
+// the state file made up for the repairer processes goes with the run
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        if let Some(path) = self.temporary_state.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// the text of a caught panic, the payload is a string for the panics raised with a message
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    }
+}
+
+// the supervisor of a lockstep repairer, a panic in the decision or the execution is caught and turned into a failure
+// the repairer still takes part in the phases of that round so the others and the master thread never block on it
+fn run_lockstep(
+    member: &RoundMember,
+    state: &Mutex<Repairer>,
+    matrix: &Matrix,
+    checks: &[AtomicBool],
+) {
+    let id = member.id;
+    let mut failure: Option<SimulationError> = None;
+    while let Some((phase, round)) = member.next_phase() {
+        match phase {
+            Phase::Decide => {
+                let decided =
+                    panic::catch_unwind(AssertUnwindSafe(|| make_decision(state, matrix, round)));
+                if let Err(payload) = decided {
+                    failure = Some(SimulationError::Panicked {
+                        repairer: id,
+                        round,
+                        message: panic_message(payload.as_ref()),
+                    });
+                }
+                member.done();
+            }
+            Phase::Execute => {
+                let exe_res = match failure.take() {
+                    // the decision has already failed, there is nothing to execute
+                    Some(error) => Err(error),
+                    None => {
+                        panic::catch_unwind(AssertUnwindSafe(|| execute(state, checks, matrix)))
+                            .unwrap_or_else(|payload| {
+                                Err(SimulationError::Panicked {
+                                    repairer: id,
+                                    round,
+                                    message: panic_message(payload.as_ref()),
+                                })
+                            })
+                    }
+                };
+                match exe_res {
+                    Ok(true) => member.done(),
+                    Ok(false) => {
+                        // the explore has ended, the master thread reads the result once the round is over
+                        member.leave();
+                        break;
+                    }
+                    Err(error) => {
+                        fail(state, checks, error);
+                        member.leave();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the proxy of a repairer process, it forwards the phases and the faults of its repairer and mirrors the reports into its state
+// a process that dies is a failure of its repairer only, the city state lives in the file and the others carry on
+fn run_process(
+    member: &RoundMember,
+    state: &Mutex<Repairer>,
+    checks: &[AtomicBool],
+    mut process: RepairerProcess,
+    faults: &[Fault],
+) {
+    let id = member.id;
+    while let Some((phase, round)) = member.next_phase() {
+        let order = match phase {
+            Phase::Decide => {
+                for fault in faults
+                    .iter()
+                    .filter(|fault| fault.repairer == id && fault.round == round)
+                {
+                    match fault.kind {
+                        FaultKind::Crash => process.kill(),
+                        kind => {
+                            let _ = process.send(&Order::Fault { kind });
+                        }
+                    }
+                }
+                Order::Decide { round }
+            }
+            Phase::Execute => Order::Execute { round },
+        };
+        let report = match process.send(&order).and_then(|_| process.receive()) {
+            Ok(report) => report,
+            Err(_) => {
+                let message = process.exit_status();
+                fail(
+                    state,
+                    checks,
+                    SimulationError::Crashed {
+                        repairer: id,
+                        round,
+                        message,
+                    },
+                );
+                member.leave();
+                break;
+            }
+        };
+
+        let mut repairer = state.lock().unwrap();
+        repairer.total_fixed = report.result.repairs;
+        repairer.current_location = report.result.final_location;
+        repairer.last_event = report.event;
+        repairer.reported = Some(report.result);
+        drop(repairer);
+        match report.outcome {
+            Ok(true) => member.done(),
+            Ok(false) => {
+                checks[id as usize].store(true, Ordering::Relaxed);
+                member.leave();
+                break;
+            }
+            Err(error) => {
+                fail(state, checks, error);
+                member.leave();
+                break;
+            }
+        }
+    }
+}
+
+// a free running repairer may panic while the master thread is watching, its poisoned state is read as it was left
+fn lock_watched(state: &Mutex<Repairer>) -> MutexGuard<'_, Repairer> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// leaves the next rounds to the others and the error to the master thread
+// the state is kept as it was when the repairer panicked, the results still read it
+fn fail(state: &Mutex<Repairer>, checks: &[AtomicBool], error: SimulationError) {
+    state.clear_poison();
+    let mut state = state.lock().unwrap();
+    checks[state.id as usize].store(true, Ordering::Relaxed);
+    state.failure = Some(error);
+}
+
+// the decide and execute loop of a free running repairer, it ends with the explore or when the run is stopped, paused in between
+fn run_free(
+    member: &RoundMember,
+    state: &Mutex<Repairer>,
+    matrix: &Matrix,
+    checks: &[AtomicBool],
+    max_rounds: Option<u32>,
+) -> Result<(), SimulationError> {
+    let id = member.id;
+    let mut round: u32 = 0;
+    while max_rounds.is_none_or(|max| round < max) && member.keep_running() {
+        let executed = panic::catch_unwind(AssertUnwindSafe(|| {
+            make_decision(state, matrix, round);
+            execute(state, checks, matrix)
+        }))
+        .unwrap_or_else(|payload| {
+            Err(SimulationError::Panicked {
+                repairer: id,
+                round,
+                message: panic_message(payload.as_ref()),
+            })
+        })?;
+        if !executed {
+            break;
+        }
+        round += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{BuiltinStrategy, CellView, RepairerView, Strategy, StrategyFactory};
+    use rand::RngCore;
+
+    // the built-in sweeps, repairer 1 panics while deciding its round 3
+    // a repairer decides once in each of its rounds, the strategy is either asked or told about the decision
+    #[derive(Debug)]
+    struct PanicInRoundThree {
+        sweep: BuiltinStrategy,
+        id: u32,
+        decisions: u32,
+    }
+
+    impl PanicInRoundThree {
+        fn decide(&mut self) {
+            if self.id == 1 && self.decisions == 3 {
+                panic!("repairer 1 broke down");
+            }
+            self.decisions += 1;
+        }
+    }
+
+    impl Strategy for PanicInRoundThree {
+        fn next_move(
+            &mut self,
+            repairer: &RepairerView,
+            cell: &CellView,
+            rng: &mut dyn RngCore,
+        ) -> Move {
+            self.decide();
+            self.sweep.next_move(repairer, cell, rng)
+        }
+
+        fn skipped(&mut self, decision: &Move) {
+            self.decide();
+            self.sweep.skipped(decision);
+        }
+    }
+
+    fn supervised_run(
+        mode: ExecutionMode,
+        on_failure: FailurePolicy,
+    ) -> Result<SimulationResult, SimulationError> {
+        Simulation::new(SimulationConfig {
+            seed: Some(77),
+            headless: true,
+            repairers: 4,
+            mode,
+            on_failure,
+            strategy: StrategyFactory::new(|id, spec| {
+                Box::new(PanicInRoundThree {
+                    sweep: BuiltinStrategy::new(spec.algorithm.clone()),
+                    id,
+                    decisions: 0,
+                })
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+        .run_to_completion()
+    }
+
+    #[test]
+    fn a_panic_aborts_the_run() {
+        for mode in [ExecutionMode::Lockstep, ExecutionMode::FreeRunning] {
+            let result = supervised_run(mode, FailurePolicy::Abort);
+            assert!(
+                matches!(
+                    result,
+                    Err(SimulationError::Panicked {
+                        repairer: 1,
+                        round: 3,
+                        ref message,
+                    }) if message == "repairer 1 broke down"
+                ),
+                "{mode}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_others_carry_on_after_a_panic() {
+        for mode in [ExecutionMode::Lockstep, ExecutionMode::FreeRunning] {
+            let result = supervised_run(mode, FailurePolicy::Continue).unwrap();
+            assert_eq!(result.outcome, Outcome::Repaired, "{mode}");
+            assert_eq!(result.failures.len(), 1, "{mode}");
+            assert!(matches!(
+                result.failures[0],
+                SimulationError::Panicked {
+                    repairer: 1,
+                    round: 3,
+                    ..
+                }
+            ));
+            assert_eq!(result.broken_left, 0, "{mode}");
+        }
+    }
+
+    fn traced_run(seed: u64) -> SimulationResult {
+        Simulation::new(SimulationConfig {
+            seed: Some(seed),
+            headless: true,
+            trace: true,
+            ..Default::default()
+        })
+        .unwrap()
+        .run_to_completion()
+        .unwrap()
+    }
+
+    #[test]
+    fn a_seed_replays_the_same_run() {
+        let first = traced_run(77);
+        for _ in 0..2 {
+            let again = traced_run(77);
+            assert_eq!(again.trace, first.trace);
+            assert_eq!(again.rounds, first.rounds);
+        }
+        assert_ne!(traced_run(78).trace, first.trace);
+    }
+
+    #[test]
+    fn the_run_ends_with_its_driver() {
+        let whole = traced_run(77);
+        let mut simulation = Simulation::new(SimulationConfig {
+            seed: Some(77),
+            headless: true,
+            trace: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let steps = simulation
+            .run_with(|stepper| {
+                let mut steps = 0;
+                while stepper.step().unwrap() {
+                    steps += 1;
+                }
+                steps
+            })
+            .unwrap();
+        assert_eq!(steps + 1, whole.rounds);
+        // the repairers are gone, a later driver finds nothing to step
+        assert!(!simulation
+            .run_with(|stepper| stepper.step().unwrap())
+            .unwrap());
+        let stepped = simulation.finish();
+        assert_eq!(stepped.outcome, whole.outcome);
+        assert_eq!(stepped.trace, whole.trace);
+
+        let mut simulation = Simulation::new(SimulationConfig {
+            seed: Some(77),
+            headless: true,
+            ..Default::default()
+        })
+        .unwrap();
+        simulation
+            .run_with(|stepper| stepper.step().unwrap())
+            .unwrap();
+        assert_eq!(simulation.outcome(), Some(Outcome::Aborted));
+        assert_eq!(simulation.finish().rounds, 1);
+    }
+}
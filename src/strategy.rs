@@ -0,0 +1,221 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use rand::{Rng, RngCore};
+
+use crate::{
+    grid::{Grid, Position},
+    models::{CellSet, CityMap, Move, MovementAlgorithm, Note, TerrainMap},
+    scenario::RepairerSpec,
+};
+
+// what a strategy can see of its repairer
+#[derive(Debug)]
+pub struct RepairerView<'a> {
+    pub id: u32,
+    pub round: u32,
+    pub location: Position,
+    pub grid: Grid,
+    pub total_broken: Option<u32>, // unknown when houses may break during the run
+    pub total_fixed: u32,
+    pub total_moves: u32,
+    pub known_repairs: &'a HashMap<u32, u32>,
+    pub map: &'a CityMap, // its own sightings merged with the maps left by the others
+    pub blocked: &'a CellSet,
+    pub terrain: &'a TerrainMap,
+}
+
+impl RepairerView<'_> {
+    // whether the movement stays on the grid and out of the blocked cells, the other moves are always possible
+    pub fn can_move(&self, movement: &Move) -> bool {
+        match movement {
+            Move::Up | Move::Down | Move::Left | Move::Right => self
+                .grid
+                .step(self.location, movement)
+                .is_some_and(|target| !self.blocked.contains(target.into())),
+            _ => true,
+        }
+    }
+}
+
+// what a strategy can see of the house its repairer stands on
+#[derive(Debug)]
+pub struct CellView<'a> {
+    pub value: u8,
+    pub notes: &'a [Note],
+}
+
+// picks the next movement of a repairer, the fixing and the end of the explore are decided before the strategy is asked
+pub trait Strategy: Send + fmt::Debug {
+    fn next_move(
+        &mut self,
+        repairer: &RepairerView,
+        cell: &CellView,
+        rng: &mut dyn RngCore,
+    ) -> Move;
+
+    // told about the decisions made without asking the strategy, a wait, a fix or the end of the explore
+    fn skipped(&mut self, _decision: &Move) {}
+}
+
+pub type StrategyBuilder = dyn Fn(u32, &RepairerSpec) -> Box<dyn Strategy> + Send + Sync;
+
+// builds the strategy of each repairer from its id and its scenario entry
+#[derive(Clone)]
+pub struct StrategyFactory(pub Arc<StrategyBuilder>);
+
+impl StrategyFactory {
+    pub fn new(
+        factory: impl Fn(u32, &RepairerSpec) -> Box<dyn Strategy> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(factory))
+    }
+
+    pub fn build(&self, id: u32, spec: &RepairerSpec) -> Box<dyn Strategy> {
+        (self.0)(id, spec)
+    }
+}
+
+impl Default for StrategyFactory {
+    fn default() -> Self {
+        Self::new(|_, spec| Box::new(BuiltinStrategy::new(spec.algorithm.clone())))
+    }
+}
+
+impl fmt::Debug for StrategyFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StrategyFactory")
+    }
+}
+
+// the eight BFS/DFS sweeps, rotating the algorithm on the corners and the edges of the matrix
+#[derive(Clone, Debug)]
+pub struct BuiltinStrategy {
+    pub algorithm: MovementAlgorithm,
+    last_move_rotated: bool,
+    last_move: Move,
+}
+
+impl BuiltinStrategy {
+    pub fn new(algorithm: MovementAlgorithm) -> Self {
+        Self {
+            algorithm,
+            last_move_rotated: false,
+            last_move: Move::Empty,
+        }
+    }
+}
+
+impl Strategy for BuiltinStrategy {
+    fn next_move(
+        &mut self,
+        repairer: &RepairerView,
+        _cell: &CellView,
+        rng: &mut dyn RngCore,
+    ) -> Move {
+        // based on the turn which will either be a breath or depth move we will find the sensitive houses that the algorithm must be rotated.
+        // the rotation is applied on the algorithm of the specific thread,
+        // if the thread is on a BFS turn and the current index is a sensitive index we rotate the BFS direction and will update the new algo on the threads state.
+
+        // getting the next move in condition that nothing is checked
+        let first = rng.gen_bool(1.0 / 3.0);
+        let mut n_move: Move = self.algorithm.get_move(first);
+        let grid = repairer.grid;
+        let location = repairer.location;
+        // on an axis of two cells the held back move runs into the next corner, the drawn one breaks the bounce
+        if self.last_move_rotated && grid.step(location, &self.last_move).is_some() {
+            n_move = self.last_move.clone();
+        }
+        self.last_move_rotated = false;
+
+        // checking the index // might rotate tha algo
+        // case 1 => corners
+        let top = grid.is_first_row(location);
+        let bottom = grid.is_last_row(location);
+        let left = grid.is_first_column(location);
+        let right = grid.is_last_column(location);
+        if (top && left && (n_move == Move::Left || n_move == Move::Up))
+            || (top && right && (n_move == Move::Right || n_move == Move::Up))
+            || (bottom && right && (n_move == Move::Right || n_move == Move::Down))
+            || (bottom && left && (n_move == Move::Left || n_move == Move::Down))
+        {
+            // updating the threads state
+            self.algorithm.rotate_algo(&n_move);
+            self.last_move_rotated = true;
+            n_move.rotate_dir();
+            self.last_move = n_move.clone();
+        } else if n_move.is_horizontal() {
+            // case 2 => edges
+            // checking the right and the left edges
+            // checking if the col value is 0 or <columns - 1>
+            if (left && n_move == Move::Left) || (right && n_move == Move::Right) {
+                self.algorithm.rotate_algo(&n_move);
+                n_move.rotate_dir();
+            }
+        } else {
+            // checking the bottom and the top edges
+            // checking if the row value is 0 or <rows - 1>
+            if (top && n_move == Move::Up) || (bottom && n_move == Move::Down) {
+                self.algorithm.rotate_algo(&n_move);
+                n_move.rotate_dir();
+            }
+        }
+
+        // case 3 => blocked cells, rotating like on the edges
+        if !repairer.can_move(&n_move) {
+            self.algorithm.rotate_algo(&n_move);
+            n_move.rotate_dir();
+            if !repairer.can_move(&n_move) {
+                // walled in on this axis, stepping aside on the other one
+                let mut sides: Vec<Move> = [Move::Up, Move::Down, Move::Right, Move::Left]
+                    .into_iter()
+                    .filter(|side| side.is_horizontal() != n_move.is_horizontal())
+                    .filter(|side| repairer.can_move(side))
+                    .collect();
+                n_move = match sides.len() {
+                    0 => Move::None, // enclosed, there is nowhere to go
+                    len => sides.swap_remove(rng.gen_range(0..len)),
+                };
+            }
+        }
+
+        n_move
+    }
+
+    // the move held back by a corner rotation is only replayed by the very next decision
+    fn skipped(&mut self, _decision: &Move) {
+        self.last_move_rotated = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simulation::{Outcome, Simulation, SimulationConfig};
+
+    // the corners of an axis of two cells used to hand the held back move to each other forever
+    #[test]
+    fn narrow_grids_get_repaired() {
+        for (rows, columns) in [(2, 7), (7, 2), (2, 3), (3, 2)] {
+            for seed in 1..=10 {
+                let result = Simulation::new(SimulationConfig {
+                    rows,
+                    columns,
+                    seed: Some(seed),
+                    headless: true,
+                    max_rounds: Some(5000),
+                    ..Default::default()
+                })
+                .unwrap()
+                .run_to_completion()
+                .unwrap();
+                assert_eq!(
+                    result.outcome,
+                    Outcome::Repaired,
+                    "{}x{} with seed {}",
+                    rows,
+                    columns,
+                    seed
+                );
+            }
+        }
+    }
+}
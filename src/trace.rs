@@ -0,0 +1,162 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    grid::{Grid, Position},
+    models::{ConfigError, Move, TraceEvent},
+    mods::{clear_terminal, print_grid},
+    scenario::Scenario,
+    simulation::MAX_DAMAGE,
+};
+
+// the move by move record of a run
+// the file holds the scenario on the first line and then one line with the events of each round
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace {
+    pub scenario: Scenario,
+    pub rounds: Vec<Vec<TraceEvent>>,
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    MissingScenario,
+    Scenario(ConfigError),
+    InvalidEvent { round: u32, repairer: u32 }, // an unknown repairer or a cell outside of the grid
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "couldn't access the trace file: {}", e),
+            Self::Format(e) => write!(f, "invalid trace file: {}", e),
+            Self::MissingScenario => write!(f, "the trace file doesn't start with a scenario"),
+            Self::Scenario(e) => write!(f, "invalid scenario in the trace file: {}", e),
+            Self::InvalidEvent { round, repairer } => write!(
+                f,
+                "the event of repairer {} in round {} doesn't fit the scenario of the trace",
+                repairer, round
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TraceError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Format(e)
+    }
+}
+
+impl From<ConfigError> for TraceError {
+    fn from(e: ConfigError) -> Self {
+        Self::Scenario(e)
+    }
+}
+
+impl Trace {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            rounds: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TraceError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &self.scenario)?;
+        writeln!(writer)?;
+        for round in self.rounds.iter() {
+            serde_json::to_writer(&mut writer, round)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // the scenario and the events are checked against each other, a replay never goes outside of the grid
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let scenario: Scenario = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err(TraceError::MissingScenario),
+        };
+        scenario.validate()?;
+        let grid = Grid::new(scenario.rows, scenario.columns);
+        let repairers = scenario.repairers.len() as u32;
+        let mut rounds: Vec<Vec<TraceEvent>> = Vec::new();
+        for line in lines {
+            let events: Vec<TraceEvent> = serde_json::from_str(&line?)?;
+            if let Some(event) = events.iter().find(|event| {
+                event.id >= repairers || !grid.contains(event.from) || !grid.contains(event.to)
+            }) {
+                return Err(TraceError::InvalidEvent {
+                    round: rounds.len() as u32,
+                    repairer: event.id,
+                });
+            }
+            rounds.push(events);
+        }
+        Ok(Self { scenario, rounds })
+    }
+
+    // renders the run frame by frame from the recorded events, without running the repairers
+    pub fn replay(&self, frame_delay: Duration) {
+        let mut cells: Vec<Vec<u8>> =
+            vec![vec![0; self.scenario.columns as usize]; self.scenario.rows as usize];
+        for (idx, (row, col)) in self.scenario.broken.iter().enumerate() {
+            cells[*row as usize][*col as usize] = self.scenario.damage_of(idx);
+        }
+        let blocked = self.scenario.blocked_cells();
+        let mut locations: Vec<Position> = self
+            .scenario
+            .repairers
+            .iter()
+            .map(|r| r.location.into())
+            .collect();
+
+        for (round, events) in self.rounds.iter().enumerate() {
+            for shock in self
+                .scenario
+                .aftershocks
+                .iter()
+                .filter(|shock| shock.round == round as u32)
+            {
+                let cell = &mut cells[shock.cell.0 as usize][shock.cell.1 as usize];
+                *cell = cell.saturating_add(shock.damage).min(MAX_DAMAGE);
+            }
+
+            clear_terminal();
+            println!("round {}/{}", round + 1, self.rounds.len());
+            print_grid(&cells, &blocked, &locations);
+            thread::sleep(frame_delay);
+
+            for event in events.iter() {
+                if let Some(damage) = event.damage {
+                    cells[event.to.row as usize][event.to.col as usize] = damage;
+                } else if event.decision == Move::Fix && event.fixed == Some(true) {
+                    cells[event.to.row as usize][event.to.col as usize] = 0;
+                }
+                locations[event.id as usize] = event.to;
+            }
+        }
+
+        clear_terminal();
+        println!("replayed {} rounds", self.rounds.len());
+        print_grid(&cells, &blocked, &locations);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{atomic::AtomicBool, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    city::Matrix,
+    models::{ConfigError, NoteDefense, RepairerResult, SimulationError, TraceEvent},
+    mods::{execute, make_decision},
+    scenario::{FaultKind, Scenario},
+    simulation::{panic_message, ExecutionMode, Simulation, SimulationConfig},
+};
+
+// what a repairer process needs to rebuild its own state, sent on the first line of its input
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorkerSetup {
+    pub id: u32,
+    pub state_file: PathBuf, // the state array of the run, created by the master process
+    pub scenario: Scenario,  // the layout of the run along with its seed
+    pub aftershock_rate: f64,
+    pub note_noise: f64,
+    pub note_defense: NoteDefense,
+}
+
+impl WorkerSetup {
+    fn config(&self) -> SimulationConfig {
+        SimulationConfig {
+            seed: self.scenario.seed,
+            headless: true,
+            scenario: Some(self.scenario.clone()),
+            aftershock_rate: self.aftershock_rate,
+            note_noise: self.note_noise,
+            note_defense: self.note_defense,
+            mode: ExecutionMode::Processes,
+            ..Default::default()
+        }
+    }
+}
+
+// a line sent by the master process to a repairer process
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "order", rename_all = "snake_case")]
+pub enum Order {
+    Decide { round: u32 },
+    Execute { round: u32 },
+    Fault { kind: FaultKind }, // applied before the next decision, nothing is answered
+}
+
+// the answer of a repairer process to a phase, the repairer is out of the run once it isn't `Ok(true)`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub outcome: Result<bool, SimulationError>,
+    pub event: Option<TraceEvent>, // what happened in the execute
+    pub result: RepairerResult,    // the state of the repairer after the phase
+}
+
+#[derive(Debug)]
+pub enum WorkerError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Config(ConfigError),
+}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "the repairer process lost its master: {}", e),
+            Self::Format(e) => write!(f, "invalid message from the master process: {}", e),
+            Self::Config(e) => write!(f, "the repairer process couldn't be set up: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+impl From<io::Error> for WorkerError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for WorkerError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Format(e)
+    }
+}
+
+impl From<ConfigError> for WorkerError {
+    fn from(e: ConfigError) -> Self {
+        Self::Config(e)
+    }
+}
+
+// the body of a repairer process, it attaches to the state file and answers the orders of the master process one by one
+// the process ends with the explore of its repairer, with its failure or once its input is closed
+pub fn run_worker(input: impl BufRead, output: impl Write) -> Result<(), WorkerError> {
+    let mut output = BufWriter::new(output);
+    let mut lines = input.lines();
+    let setup: WorkerSetup = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Ok(()),
+    };
+    let matrix = Matrix::attach(&setup.state_file)?;
+    let repairer = Mutex::new(Simulation::repairer(&setup.config(), setup.id)?);
+    // only the flag of this repairer is ever set, the master process keeps the real ones
+    let checks: Vec<AtomicBool> = (0..matrix.repairers())
+        .map(|_| AtomicBool::new(false))
+        .collect();
+
+    for line in lines {
+        let order: Order = serde_json::from_str(&line?)?;
+        let outcome = match order {
+            Order::Fault { kind } => {
+                let mut repairer = repairer.lock().unwrap();
+                match kind {
+                    FaultKind::Pause { rounds } => repairer.busy_rounds += rounds,
+                    FaultKind::Amnesia => repairer.forget(),
+                    // the master process takes care of the others
+                    FaultKind::Stop | FaultKind::Crash => {}
+                }
+                continue;
+            }
+            Order::Decide { round } => panic::catch_unwind(AssertUnwindSafe(|| {
+                make_decision(&repairer, &matrix, round)
+            }))
+            .map_err(|payload| SimulationError::Panicked {
+                repairer: setup.id,
+                round,
+                message: panic_message(payload.as_ref()),
+            }),
+            Order::Execute { round } => {
+                panic::catch_unwind(AssertUnwindSafe(|| execute(&repairer, &checks, &matrix)))
+                    .unwrap_or_else(|payload| {
+                        Err(SimulationError::Panicked {
+                            repairer: setup.id,
+                            round,
+                            message: panic_message(payload.as_ref()),
+                        })
+                    })
+            }
+        };
+
+        // a panic has left the state as it was, the result still reads it
+        repairer.clear_poison();
+        let mut state = repairer.lock().unwrap();
+        let report = Report {
+            event: state.last_event.take(),
+            result: state.to_result(),
+            outcome,
+        };
+        serde_json::to_writer(&mut output, &report)?;
+        writeln!(output)?;
+        output.flush()?;
+        if report.outcome != Ok(true) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// the master side of a repairer process, the orders go to its input and the reports come from its output
+pub struct RepairerProcess {
+    child: Child,
+    orders: Option<BufWriter<ChildStdin>>, // closed to end the process
+    reports: BufReader<ChildStdout>,
+}
+
+impl RepairerProcess {
+    // starts <program> with the `worker` argument and sends it its setup
+    pub fn spawn(program: &Path, setup: &WorkerSetup) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .arg("worker")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(orders), Some(reports)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other(
+                "the pipes of the repairer process are missing",
+            ));
+        };
+        let mut process = Self {
+            child,
+            orders: Some(BufWriter::new(orders)),
+            reports: BufReader::new(reports),
+        };
+        process.write_line(setup)?;
+        Ok(process)
+    }
+
+    fn write_line(&mut self, message: &impl Serialize) -> io::Result<()> {
+        let Some(orders) = self.orders.as_mut() else {
+            return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+        };
+        serde_json::to_writer(&mut *orders, message)?;
+        writeln!(orders)?;
+        orders.flush()
+    }
+
+    pub fn send(&mut self, order: &Order) -> io::Result<()> {
+        self.write_line(order)
+    }
+
+    // blocks until the process answers, an error once the process has died
+    pub fn receive(&mut self) -> io::Result<Report> {
+        let mut line = String::new();
+        if self.reports.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn kill(&mut self) {
+        let _ = self.child.kill();
+    }
+
+    // how the process has ended, it is waited for
+    pub fn exit_status(&mut self) -> String {
+        self.orders = None;
+        match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+}
+
+// a process left behind is told to end by closing its input
+impl Drop for RepairerProcess {
+    fn drop(&mut self) {
+        self.orders = None;
+        let _ = self.child.wait();
+    }
+}